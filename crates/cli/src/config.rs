@@ -1,4 +1,5 @@
 use std::net::IpAddr;
+use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
@@ -6,4 +7,18 @@ use serde::{Deserialize, Serialize};
 pub struct Config {
     pub addr: IpAddr,
     pub port: u16,
+    #[serde(default)]
+    pub backend: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub https: bool,
+    #[serde(default)]
+    pub ca_certificate: Option<PathBuf>,
+    #[serde(default)]
+    pub exclusive_ca_certificate: Option<PathBuf>,
+    #[serde(default)]
+    pub signing_key: Option<PathBuf>,
+    #[serde(default)]
+    pub staging_directory: Option<PathBuf>,
 }
@@ -0,0 +1,88 @@
+use std::path::Path;
+use std::process::Command;
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct Metadata {
+    pub commit: Option<String>,
+    pub branch: Option<String>,
+    pub author: Option<String>,
+    pub message: Option<String>,
+    pub dirty: Option<bool>,
+    pub detached: bool,
+    pub upstream: Option<String>,
+    pub unpushed: Option<u32>,
+    pub allow_dirty: bool,
+}
+
+impl Metadata {
+    pub fn inspect(dir: &Path, allow_dirty: bool) -> Option<Self> {
+        let commit = git(dir, &["rev-parse", "HEAD"])?;
+        let branch = git(dir, &["rev-parse", "--abbrev-ref", "HEAD"]).filter(|b| b != "HEAD");
+        let upstream = branch.as_ref().and_then(|_| {
+            git(
+                dir,
+                &[
+                    "rev-parse",
+                    "--abbrev-ref",
+                    "--symbolic-full-name",
+                    "@{upstream}",
+                ],
+            )
+        });
+        let unpushed = upstream.as_ref().and_then(|_| {
+            git(dir, &["rev-list", "--count", "@{upstream}..HEAD"])?
+                .parse()
+                .ok()
+        });
+        Some(Self {
+            commit: Some(commit),
+            detached: branch.is_none(),
+            branch,
+            author: git(dir, &["log", "-1", "--format=%an <%ae>"]),
+            message: git(dir, &["log", "-1", "--format=%B"]),
+            dirty: git_output(dir, &["status", "--porcelain"]).map(|s| !s.trim().is_empty()),
+            upstream,
+            unpushed,
+            allow_dirty,
+        })
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.dirty == Some(true) {
+            problems.push(String::from("the working tree has uncommitted changes"));
+        }
+        match (&self.branch, &self.upstream, self.unpushed) {
+            (None, _, _) => problems.push(String::from("HEAD is detached")),
+            (Some(branch), None, _) => {
+                problems.push(format!("branch {} has no upstream branch", branch))
+            }
+            (_, Some(upstream), Some(unpushed)) if unpushed > 0 => problems.push(format!(
+                "{} commits are not pushed to {}",
+                unpushed, upstream
+            )),
+            _ => {}
+        }
+        problems
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    git_output(dir, args)
+        .map(|output| output.trim().to_string())
+        .filter(|output| !output.is_empty())
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -1,19 +1,39 @@
 pub mod config;
+pub mod git;
+pub mod pack;
+pub mod response;
+pub mod sign;
+pub mod upload;
 
-use std::env::current_dir;
-use std::fs::{canonicalize, File};
-use std::path::PathBuf;
+use std::env::{current_dir, temp_dir};
+use std::error::Error;
+use std::fs::{canonicalize, copy, read};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
 
 use crate::config::Config;
-use clap::{Parser, Subcommand};
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use reqwest::blocking::multipart::Form;
-use reqwest::blocking::Client;
+use crate::git::Metadata;
+use crate::response::Response;
+use clap::{Args, Parser, Subcommand};
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+use reqwest::Certificate;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
 
 #[derive(Debug, Parser)]
 #[clap(version = "0.1.0", about = "A command line interface for the daemon.")]
 pub struct Cli {
+    #[arg(long, global = true, help = "The backend to operate on.")]
+    backend: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Return once the daemon accepted the job instead of waiting for it."
+    )]
+    detach: bool,
     #[command(subcommand)]
     sub_cmd: SubCommand,
 }
@@ -29,66 +49,427 @@ enum SubCommand {
     #[clap(name = "restart", about = "Restart the daemon.")]
     Restart,
     #[clap(name = "update", about = "Update the daemon.")]
-    Update {
-        #[arg(help = "The directory to update.")]
-        dir: Option<PathBuf>,
+    Update(UploadArgs),
+    #[clap(
+        name = "deploy",
+        about = "Upload, build and switch to a new release, rolling back if it does not start."
+    )]
+    Deploy(UploadArgs),
+    #[clap(name = "releases", about = "List the deployed releases.")]
+    Releases,
+    #[clap(
+        name = "rollback",
+        about = "Roll back to a previously deployed release."
+    )]
+    Rollback {
+        #[arg(help = "The id of the release to roll back to.")]
+        id: String,
     },
+    #[clap(name = "logs", about = "Show the output of the backend.")]
+    Logs {
+        #[arg(long, help = "Show only the last N lines.")]
+        tail: Option<usize>,
+        #[arg(long, help = "Show only lines written since an RFC 3339 timestamp.")]
+        since: Option<String>,
+        #[arg(
+            long,
+            help = "Show only the given comma-separated streams (stdout, stderr, build)."
+        )]
+        stream: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "Stream the backend and build output line by line as it is written."
+        )]
+        follow: bool,
+    },
+    #[clap(
+        name = "keygen",
+        about = "Generate an Ed25519 key to sign the uploaded archives."
+    )]
+    Keygen {
+        #[arg(help = "The file to write the private key to.")]
+        path: PathBuf,
+    },
+    #[clap(name = "job", about = "Get the progress of a job.")]
+    Job {
+        #[arg(help = "The id of the job.")]
+        id: u64,
+    },
+}
+
+#[derive(Debug, Args)]
+struct UploadArgs {
+    #[arg(help = "The directory to upload.")]
+    dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Upload even with uncommitted changes, unpushed commits or a detached HEAD."
+    )]
+    allow_dirty: bool,
+    #[arg(
+        long,
+        help = "Exclude files matching a gitignore-style pattern, on top of .gitignore and .daemonignore."
+    )]
+    exclude: Vec<String>,
+    #[arg(
+        long,
+        help = "Upload a directory excluded by default, such as target or build, unless it is ignored."
+    )]
+    include: Vec<String>,
+    #[arg(
+        long,
+        help = "List the files that would be uploaded without uploading them."
+    )]
+    dry_run: bool,
+    #[arg(long, help = "Keep a copy of the uploaded archive at the given path.")]
+    keep_archive: Option<PathBuf>,
+}
+
+enum Uploaded {
+    DryRun(String),
+    Complete { id: String, body: Value },
 }
 
 impl Cli {
-    const FIELD_NAME: &'static str = "spring-boot-tar-gz-archive";
-
-    pub fn handle(self, config: Config) -> String {
-        let prefix = format!("http://{}:{}/backend", config.addr, config.port);
-        let client = Client::builder()
-            .timeout(None)
-            .build()
-            .expect("Failed to build client");
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn handle(self, config: Config) -> Result<String, String> {
+        if let SubCommand::Keygen { path } = &self.sub_cmd {
+            return sign::generate(path).map(|public_key| {
+                format!(
+                    "Wrote the private key to {}, set it as `signing_key` in the cli config.\n\
+                     Add the public key to `trusted_keys` in the daemon config:\n{}",
+                    path.display(),
+                    public_key
+                )
+            });
+        }
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &config.token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .expect("Invalid token in config");
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        let mut builder = Client::builder().timeout(None).default_headers(headers);
+        match (&config.ca_certificate, &config.exclusive_ca_certificate) {
+            (Some(_), Some(_)) => {
+                return Err(String::from(
+                    "Set either `ca_certificate` or `exclusive_ca_certificate` in the cli config, not both.",
+                ))
+            }
+            (Some(path), None) => builder = builder.add_root_certificate(Self::certificate(path)),
+            (None, Some(path)) => {
+                builder = builder
+                    .tls_built_in_root_certs(false)
+                    .add_root_certificate(Self::certificate(path))
+            }
+            (None, None) => {}
+        }
+        let client = builder.build().expect("Failed to build client");
+        let scheme = if config.https { "https" } else { "http" };
+        let base = format!("{}://{}:{}", scheme, config.addr, config.port);
+        let jobs = format!("{}/jobs", base);
+        if let SubCommand::Job { id } = self.sub_cmd {
+            return Self::send(client.get(format!("{}/{}", jobs, id)));
+        }
+        let Some(backend) = self.backend.or(config.backend.clone()) else {
+            return Err(String::from(
+                "No backend specified, pass --backend or set `backend` in the cli config.",
+            ));
+        };
+        let prefix = format!("{}/backends/{}", base, backend);
+        let wait = |request| Self::wait(&client, &jobs, request, self.detach);
         match self.sub_cmd {
-            SubCommand::Status => client.get(prefix).send().unwrap().text().unwrap(),
-            SubCommand::Start => client
-                .patch(format!("{}/start", prefix))
-                .send()
-                .unwrap()
-                .text()
-                .unwrap(),
-            SubCommand::Stop => client
-                .patch(format!("{}/stop", prefix))
-                .send()
-                .unwrap()
-                .text()
-                .unwrap(),
-            SubCommand::Restart => client
-                .patch(format!("{}/restart", prefix))
-                .send()
-                .unwrap()
+            SubCommand::Status => Self::send(client.get(prefix)),
+            SubCommand::Start => wait(client.patch(format!("{}/start", prefix))),
+            SubCommand::Stop => Self::send(client.patch(format!("{}/stop", prefix))),
+            SubCommand::Restart => wait(client.patch(format!("{}/restart", prefix))),
+            SubCommand::Update(args) => match Self::upload(args, &config, &client, &prefix)? {
+                Uploaded::DryRun(summary) => Ok(summary),
+                Uploaded::Complete { id, body } => wait(
+                    client
+                        .post(format!("{}/uploads/{}/complete", prefix, id))
+                        .json(&body),
+                ),
+            },
+            SubCommand::Deploy(args) => match Self::upload(args, &config, &client, &prefix)? {
+                Uploaded::DryRun(summary) => Ok(summary),
+                Uploaded::Complete { id, mut body } => {
+                    body["upload"] = json!(id);
+                    wait(client.post(format!("{}/deploy", prefix)).json(&body))
+                }
+            },
+            SubCommand::Releases => Self::send(client.get(format!("{}/releases", prefix))),
+            SubCommand::Rollback { id } => {
+                wait(client.patch(format!("{}/rollback/{}", prefix, id)))
+            }
+            SubCommand::Logs {
+                tail,
+                since,
+                stream,
+                follow,
+            } => {
+                let mut query = Vec::new();
+                if let Some(tail) = tail {
+                    query.push(("tail", tail.to_string()));
+                }
+                if let Some(since) = since {
+                    query.push(("since", since));
+                }
+                if let Some(stream) = stream {
+                    query.push(("stream", stream));
+                }
+                let logs = format!("{}/logs", prefix);
+                let request = client.get(&logs).query(&query);
+                if !follow {
+                    return Self::fetch(request).map(|response| Self::lines(&response));
+                }
+                if tail.is_some() {
+                    println!("{}", Self::lines(&Self::fetch(request)?));
+                }
+                let query = query.into_iter().filter(|(key, _)| *key == "stream");
+                Self::follow(
+                    client
+                        .get(format!("{}/stream", logs))
+                        .query(&query.collect::<Vec<_>>()),
+                )
+            }
+            SubCommand::Job { .. } | SubCommand::Keygen { .. } => unreachable!(),
+        }
+    }
+
+    fn upload(
+        args: UploadArgs,
+        config: &Config,
+        client: &Client,
+        prefix: &str,
+    ) -> Result<Uploaded, String> {
+        let UploadArgs {
+            dir,
+            allow_dirty,
+            exclude,
+            include,
+            dry_run,
+            keep_archive,
+        } = args;
+        let cwd = current_dir().unwrap();
+        let dir = canonicalize(cwd.join(dir.unwrap_or(PathBuf::from(".")))).unwrap();
+        let keep_archive = keep_archive.map(|path| cwd.join(path));
+        let mut entries = pack::collect(&dir, &exclude, &include)?;
+        entries.retain(|entry| Some(dir.join(&entry.path)) != keep_archive);
+        let size = entries.iter().map(|entry| entry.size).sum();
+        if dry_run {
+            for entry in &entries {
+                println!("{}", entry.path.display());
+            }
+            return Ok(Uploaded::DryRun(format!(
+                "{} files ({}) would be uploaded",
+                entries.len(),
+                pack::human(size)
+            )));
+        }
+        let mut metadata = None;
+        match Metadata::inspect(&dir, allow_dirty) {
+            Some(inspected) => {
+                println!(
+                    "commit: {} on {}",
+                    inspected.commit.as_deref().unwrap_or("unknown"),
+                    inspected.branch.as_deref().unwrap_or("detached HEAD")
+                );
+                let problems = inspected.problems();
+                if !problems.is_empty() {
+                    let problems = problems.join("\n  ");
+                    if !allow_dirty {
+                        return Err(format!(
+                            "Refusing to upload {}:\n  {}\nCommit and push first, or pass --allow-dirty.",
+                            dir.display(),
+                            problems
+                        ));
+                    }
+                    println!("uploading anyway (--allow-dirty):\n  {}", problems);
+                }
+                metadata = Some(inspected);
+            }
+            None => {
+                println!("warning: not a git repository, the uploaded state cannot be verified")
+            }
+        }
+        let staging = config.staging_directory.clone().unwrap_or_else(temp_dir);
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        let archive = tempfile::Builder::new()
+            .prefix(&format!("{}-", name))
+            .suffix(".tar.gz")
+            .tempfile_in(&staging)
+            .map_err(|e| {
+                format!(
+                    "Failed to create the archive in {}: {}",
+                    staging.display(),
+                    e
+                )
+            })?;
+        println!("compressing directory: {:?}", &dir);
+        println!("temp file path: {:?}", archive.path());
+        let compressed = pack::pack(&dir, &entries, archive.path())
+            .map_err(|e| format!("Failed to create the archive: {}", e))?;
+        println!(
+            "packed {} files ({}), {} compressed",
+            entries.len(),
+            pack::human(size),
+            pack::human(compressed)
+        );
+        if let Some(path) = &keep_archive {
+            copy(archive.path(), path)
+                .map_err(|e| format!("Failed to keep the archive at {}: {}", path.display(), e))?;
+            println!("kept archive at {:?}", path);
+        }
+        let (id, sha256) = upload::upload(client, prefix, archive.path())?;
+        let signature = match &config.signing_key {
+            Some(path) => Some(sign::sign(path, &sha256)?),
+            None => None,
+        };
+        Ok(Uploaded::Complete {
+            id,
+            body: json!({ "metadata": metadata, "signature": signature }),
+        })
+    }
+
+    fn certificate(path: &Path) -> Certificate {
+        let pem = read(path).expect("Failed to read certificate.");
+        Certificate::from_pem(&pem).expect("Invalid certificate.")
+    }
+
+    fn describe(e: &dyn Error) -> String {
+        let mut msg = e.to_string();
+        let mut source = e.source();
+        while let Some(e) = source {
+            msg.push_str(&format!(": {}", e));
+            source = e.source();
+        }
+        msg
+    }
+
+    fn send(request: RequestBuilder) -> Result<String, String> {
+        Self::fetch(request).map(|response| Self::output(&response))
+    }
+
+    fn wait(
+        client: &Client,
+        jobs: &str,
+        request: RequestBuilder,
+        detach: bool,
+    ) -> Result<String, String> {
+        let response = Self::fetch(request)?;
+        let id = response
+            .data
+            .as_ref()
+            .and_then(|data| data.get("id"))
+            .and_then(Value::as_u64);
+        let Some(id) = id.filter(|_| !detach) else {
+            return Ok(Self::output(&response));
+        };
+        println!("{}", response.message);
+        let mut printed = 0;
+        loop {
+            sleep(Self::POLL_INTERVAL);
+            let job = Self::fetch(client.get(format!("{}/{}", jobs, id)))?
+                .data
+                .unwrap_or_default();
+            let steps = job["steps"].as_array().cloned().unwrap_or_default();
+            for step in steps.iter().skip(printed) {
+                println!("  {}", step.as_str().unwrap_or_default());
+            }
+            printed = steps.len();
+            if job["status"] != "running" {
+                let result = serde_json::from_value::<Response>(job["result"].clone())
+                    .map_err(|e| format!("Invalid job result: {}", e))?;
+                return if result.success() {
+                    Ok(Self::output(&result))
+                } else {
+                    Err(Self::output(&result))
+                };
+            }
+        }
+    }
+
+    fn lines(response: &Response) -> String {
+        let lines = response
+            .data
+            .as_ref()
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        lines
+            .iter()
+            .map(|line| {
+                format!(
+                    "[{}] {}",
+                    line["stream"].as_str().unwrap_or_default(),
+                    line["text"].as_str().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn output(response: &Response) -> String {
+        response.to_string().trim_end().to_string()
+    }
+
+    fn fetch(request: RequestBuilder) -> Result<Response, String> {
+        let response = request
+            .header(ACCEPT, "application/json")
+            .send()
+            .map_err(|e| format!("Failed to send request: {}", Self::describe(&e)))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+        Self::parse(status, &text)
+    }
+
+    fn parse(status: StatusCode, text: &str) -> Result<Response, String> {
+        match serde_json::from_str::<Response>(text) {
+            Ok(response) if response.success() => Ok(response),
+            Ok(response) if status == StatusCode::UNAUTHORIZED => Err(format!(
+                "{}\nCheck the `token` field in the cli config.",
+                Self::output(&response)
+            )),
+            Ok(response) => Err(Self::output(&response)),
+            Err(_) => Err(format!("{}: {}", status, text)),
+        }
+    }
+
+    fn follow(request: RequestBuilder) -> Result<String, String> {
+        let response = request
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .map_err(|e| format!("Failed to send request: {}", Self::describe(&e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response
                 .text()
-                .unwrap(),
-            SubCommand::Update { dir } => {
-                let cwd = current_dir().unwrap();
-                let dir = canonicalize(cwd.join(dir.unwrap_or(PathBuf::from(".")))).unwrap();
-                println!("compressing directory: {:?}", &dir);
-                let temp_path = cwd.join(format!(
-                    "{}.tar.gz",
-                    dir.file_stem().unwrap().to_string_lossy()
-                ));
-                println!("temp file path: {:?}", &temp_path);
-                {
-                    let temp = File::create(&temp_path).expect("Failed to create temp file.");
-                    let enc = GzEncoder::new(&temp, Compression::default());
-                    let mut tar = tar::Builder::new(enc);
-                    tar.append_dir_all("", &dir).expect("Failed to append.");
+                .map_err(|e| format!("Failed to read response: {}", e))?;
+            return Self::parse(status, &text).map(|response| Self::output(&response));
+        }
+        let mut event = String::new();
+        for line in BufReader::new(response).lines() {
+            let line = line.map_err(|e| format!("Failed to read log stream: {}", e))?;
+            if let Some(name) = line.strip_prefix("event:") {
+                event = name.trim().to_string();
+            } else if let Some(data) = line.strip_prefix("data:") {
+                let data = data.trim_start();
+                match serde_json::from_str::<Value>(data) {
+                    Ok(line) if event != "lagged" => println!(
+                        "[{}] {}",
+                        line["stream"].as_str().unwrap_or(&event),
+                        line["text"].as_str().unwrap_or_default()
+                    ),
+                    _ => eprintln!("[{}] {}", event, data),
                 }
-                let form = Form::new().file(Self::FIELD_NAME, temp_path).unwrap();
-
-                client
-                    .put(prefix)
-                    .multipart(form)
-                    .send()
-                    .unwrap()
-                    .text()
-                    .unwrap()
             }
         }
+        Ok(String::from("Log stream closed"))
     }
 }
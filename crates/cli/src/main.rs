@@ -1,5 +1,6 @@
 use std::fs::read_to_string;
 use std::io;
+use std::process::exit;
 
 use clap::Parser;
 use cli::Cli;
@@ -8,6 +9,12 @@ const CONFIG_PATH: &str = "config/cli/config.json";
 
 fn main() -> io::Result<()> {
     let config = serde_json::from_str(&read_to_string(CONFIG_PATH)?).unwrap();
-    println!("{}", Cli::parse().handle(config));
+    match Cli::parse().handle(config) {
+        Ok(output) => println!("{}", output),
+        Err(output) => {
+            eprintln!("{}", output);
+            exit(1);
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,107 @@
+use std::fs::{metadata, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use tar::HeaderMode;
+
+const IGNORE_FILE: &str = ".daemonignore";
+const DEFAULT_EXCLUDES: [&str; 5] = [".git/", "/target/", "/build/", ".idea/", ".gradle/"];
+const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+#[derive(Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+pub fn collect(dir: &Path, excludes: &[String], includes: &[String]) -> Result<Vec<Entry>, String> {
+    let name = |exclude: &str| exclude.trim_matches('/').to_string();
+    let defaults = DEFAULT_EXCLUDES.map(name);
+    for include in includes {
+        if !defaults.contains(&name(include)) {
+            return Err(format!(
+                "{} is not excluded by default, only {} can be included",
+                include,
+                defaults.join(", ")
+            ));
+        }
+    }
+    let mut overrides = OverrideBuilder::new(dir);
+    for exclude in DEFAULT_EXCLUDES
+        .iter()
+        .copied()
+        .filter(|exclude| {
+            !includes
+                .iter()
+                .any(|include| name(include) == name(exclude))
+        })
+        .chain(excludes.iter().map(String::as_str))
+    {
+        overrides
+            .add(&format!("!{}", exclude))
+            .map_err(|e| format!("Invalid exclude pattern {}: {}", exclude, e))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| format!("Invalid exclude patterns: {}", e))?;
+    let walker = WalkBuilder::new(dir)
+        .hidden(false)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .overrides(overrides)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    let mut entries = Vec::new();
+    for entry in walker {
+        let entry = entry.map_err(|e| format!("Failed to walk {}: {}", dir.display(), e))?;
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .strip_prefix(dir)
+            .expect("Walked outside of the directory")
+            .to_path_buf();
+        let size = entry
+            .metadata()
+            .map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?
+            .len();
+        entries.push(Entry { path, size });
+    }
+    Ok(entries)
+}
+
+pub fn pack(dir: &Path, entries: &[Entry], dest: &Path) -> io::Result<u64> {
+    let file = File::create(dest)?;
+    let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    tar.mode(HeaderMode::Deterministic);
+    tar.follow_symlinks(false);
+    for entry in entries {
+        tar.append_path_with_name(dir.join(&entry.path), &entry.path)?;
+    }
+    tar.into_inner()?.finish()?;
+    Ok(metadata(dest)?.len())
+}
+
+pub fn human(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", size, unit)
+}
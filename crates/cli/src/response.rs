@@ -0,0 +1,78 @@
+use std::fmt::{self, Write};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+pub struct Response {
+    pub status: String,
+    pub message: String,
+    pub error: Option<String>,
+    pub timestamp: String,
+    pub data: Option<Value>,
+}
+
+impl Response {
+    pub fn success(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            Some(kind) => writeln!(f, "[{}] error ({}): {}", self.timestamp, kind, self.message)?,
+            None => writeln!(f, "[{}] {}", self.timestamp, self.message)?,
+        }
+        if let Some(data) = &self.data {
+            let mut out = String::new();
+            pretty(&mut out, data, 0)?;
+            f.write_str(&out)?;
+        }
+        Ok(())
+    }
+}
+
+fn pretty(out: &mut String, value: &Value, indent: usize) -> fmt::Result {
+    let pad = "  ".repeat(indent);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    Value::Object(_) | Value::Array(_) => {
+                        writeln!(out, "{}{}:", pad, key)?;
+                        pretty(out, value, indent + 1)?;
+                    }
+                    Value::String(s) if s.contains('\n') => {
+                        writeln!(out, "{}{}: |", pad, key)?;
+                        for line in s.lines() {
+                            writeln!(out, "{}  {}", pad, line)?;
+                        }
+                    }
+                    _ => writeln!(out, "{}{}: {}", pad, key, scalar(value))?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                match item {
+                    Value::Object(_) | Value::Array(_) => {
+                        writeln!(out, "{}-", pad)?;
+                        pretty(out, item, indent + 1)?;
+                    }
+                    _ => writeln!(out, "{}- {}", pad, scalar(item))?,
+                }
+            }
+        }
+        _ => writeln!(out, "{}{}", pad, scalar(value))?,
+    }
+    Ok(())
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => String::from("-"),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
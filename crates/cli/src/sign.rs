@@ -0,0 +1,31 @@
+use std::fs::{read_to_string, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use ed25519_dalek::{Signer, SigningKey, SECRET_KEY_LENGTH};
+use rand_core::OsRng;
+
+pub fn generate(path: &Path) -> Result<String, String> {
+    let key = SigningKey::generate(&mut OsRng);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    writeln!(file, "{}", hex::encode(key.to_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(hex::encode(key.verifying_key().to_bytes()))
+}
+
+pub fn sign(path: &Path, sha256: &str) -> Result<String, String> {
+    let content = read_to_string(path)
+        .map_err(|e| format!("Failed to read signing key {}: {}", path.display(), e))?;
+    let mut secret = [0; SECRET_KEY_LENGTH];
+    hex::decode_to_slice(content.trim(), &mut secret)
+        .map_err(|e| format!("Invalid signing key {}: {}", path.display(), e))?;
+    let digest = hex::decode(sha256).map_err(|e| format!("Invalid SHA-256 hash: {}", e))?;
+    let signature = SigningKey::from_bytes(&secret).sign(&digest);
+    Ok(hex::encode(signature.to_bytes()))
+}
@@ -0,0 +1,185 @@
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::ACCEPT;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::pack::human;
+use crate::response::Response;
+use crate::Cli;
+
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const RETRIES: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Deserialize)]
+struct Upload {
+    id: String,
+    chunks: u64,
+    received: Vec<u64>,
+}
+
+pub fn upload(client: &Client, prefix: &str, archive: &Path) -> Result<(String, String), String> {
+    let uploads = format!("{}/uploads", prefix);
+    let mut file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read archive: {}", e))?
+        .len();
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| format!("Failed to hash archive: {}", e))?;
+    let sha256 = hex::encode(hasher.finalize());
+    println!("archive sha256: {}", sha256);
+    let body = json!({ "size": size, "sha256": sha256, "chunk_size": CHUNK_SIZE });
+    let upload = request("Starting the upload", || client.post(&uploads).json(&body))?;
+    let upload = serde_json::from_value::<Upload>(upload.data.unwrap_or_default())
+        .map_err(|e| format!("Invalid upload: {}", e))?;
+    if !upload.received.is_empty() {
+        println!(
+            "resuming upload {}: {} of {} chunks already uploaded",
+            upload.id,
+            upload.received.len(),
+            upload.chunks
+        );
+    }
+    let mut progress = Progress::new(size);
+    for index in 0..upload.chunks {
+        let offset = index * CHUNK_SIZE;
+        let len = CHUNK_SIZE.min(size - offset);
+        if upload.received.contains(&index) {
+            progress.advance(len);
+            continue;
+        }
+        let mut chunk = vec![0; len as usize];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+        let hash = hex::encode(Sha256::digest(&chunk));
+        let url = format!("{}/{}/chunks/{}", uploads, upload.id, index);
+        request(&format!("Uploading chunk {}", index), || {
+            client
+                .put(&url)
+                .query(&[("sha256", &hash)])
+                .timeout(CHUNK_TIMEOUT)
+                .body(chunk.clone())
+        })
+        .map_err(|e| {
+            progress.finish();
+            format!("{}\nRun the same command again to resume the upload.", e)
+        })?;
+        progress.advance(len);
+    }
+    progress.finish();
+    Ok((upload.id, sha256))
+}
+
+fn request<F: Fn() -> RequestBuilder>(what: &str, build: F) -> Result<Response, String> {
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let error = match build().header(ACCEPT, "application/json").send() {
+            Ok(response) => {
+                let status = response.status();
+                let text = response
+                    .text()
+                    .map_err(|e| format!("Failed to read response: {}", e));
+                match text.map(|text| (serde_json::from_str::<Response>(&text), text)) {
+                    Ok((Ok(response), _)) if response.success() => return Ok(response),
+                    Ok((parsed, text)) => {
+                        let msg = match parsed {
+                            Ok(response) => response.to_string().trim_end().to_string(),
+                            Err(_) => format!("{}: {}", status, text),
+                        };
+                        if !retryable(status) {
+                            return Err(msg);
+                        }
+                        msg
+                    }
+                    Err(e) => e,
+                }
+            }
+            Err(e) => Cli::describe(&e),
+        };
+        if attempt == RETRIES {
+            return Err(format!(
+                "{} failed after {} attempts: {}",
+                what, RETRIES, error
+            ));
+        }
+        if io::stderr().is_terminal() {
+            eprintln!();
+        }
+        eprintln!(
+            "{} failed: {}, retrying in {}s ({}/{})",
+            what,
+            error,
+            delay.as_secs(),
+            attempt,
+            RETRIES
+        );
+        sleep(delay);
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+fn retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+struct Progress {
+    total: u64,
+    done: u64,
+    terminal: bool,
+}
+
+impl Progress {
+    fn new(total: u64) -> Self {
+        let progress = Self {
+            total,
+            done: 0,
+            terminal: io::stderr().is_terminal(),
+        };
+        progress.draw();
+        progress
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        self.draw();
+    }
+
+    fn draw(&self) {
+        if !self.terminal {
+            return;
+        }
+        let ratio = self.done as f64 / self.total.max(1) as f64;
+        let filled = (ratio * BAR_WIDTH as f64) as usize;
+        eprint!(
+            "\r[{}{}] {:>3}% {} / {}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            (ratio * 100.0) as u64,
+            human(self.done),
+            human(self.total)
+        );
+        let _ = io::stderr().flush();
+    }
+
+    fn finish(&self) {
+        if self.terminal {
+            eprintln!();
+        }
+    }
+}
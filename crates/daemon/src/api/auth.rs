@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::api::response::{ApiResponse, ErrorKind};
+
+#[derive(Debug, Clone)]
+pub struct Tokens(Arc<Vec<[u8; 32]>>);
+
+impl Tokens {
+    pub fn new<S: AsRef<str>>(hashes: &[S]) -> Result<Self, String> {
+        let mut tokens = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            let mut token = [0; 32];
+            hex::decode_to_slice(hash.as_ref(), &mut token)
+                .map_err(|e| format!("Invalid token hash {:?}: {}", hash.as_ref(), e))?;
+            tokens.push(token);
+        }
+        Ok(Self(Arc::new(tokens)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn verify(&self, token: &str) -> bool {
+        let hash: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        self.0.iter().fold(false, |found, expected| {
+            let diff = expected
+                .iter()
+                .zip(&hash)
+                .fold(0, |acc, (a, b)| acc | (a ^ b));
+            found | (diff == 0)
+        })
+    }
+}
+
+pub async fn authorize(State(tokens): State<Tokens>, request: Request, next: Next) -> Response {
+    if tokens.is_empty() {
+        return next.run(request).await;
+    }
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if tokens.verify(token.trim()) => next.run(request).await,
+        Some(_) => unauthorized("Invalid bearer token"),
+        None => unauthorized("Missing bearer token in the Authorization header"),
+    }
+}
+
+fn unauthorized(message: &'static str) -> Response {
+    warn!("Rejected unauthorized request");
+    let mut response = ApiResponse::error(ErrorKind::Unauthorized, message).into_response();
+    response
+        .headers_mut()
+        .insert(WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const TOKEN: &str = "secret";
+
+    fn tokens() -> Tokens {
+        Tokens::new(&[
+            hex::encode(Sha256::digest("other")),
+            hex::encode(Sha256::digest(TOKEN)),
+        ])
+        .unwrap()
+    }
+
+    async fn status(authorization: Option<&str>) -> StatusCode {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(tokens(), authorize));
+        let mut request = Request::builder().uri("/");
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.status()
+    }
+
+    #[test]
+    fn rejects_malformed_token_hashes() {
+        assert!(Tokens::new(&["not hex"]).is_err());
+        assert!(Tokens::new(&["abcd"]).is_err());
+        assert!(Tokens::new(&[hex::encode([0; 33])]).is_err());
+    }
+
+    #[test]
+    fn verifies_listed_tokens_only() {
+        let tokens = tokens();
+        assert!(tokens.verify(TOKEN));
+        assert!(tokens.verify("other"));
+        assert!(!tokens.verify("wrong"));
+        assert!(!tokens.verify(""));
+        assert!(!tokens.verify(&hex::encode(Sha256::digest(TOKEN))));
+    }
+
+    #[tokio::test]
+    async fn accepts_a_listed_token() {
+        assert_eq!(status(Some("Bearer secret")).await, StatusCode::OK);
+        assert_eq!(status(Some("Bearer  secret ")).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_missing_or_malformed_tokens() {
+        assert_eq!(status(Some("Bearer wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("secret")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("Basic secret")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(Some("bearer secret")).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn lets_everything_through_without_tokens() {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(
+                Tokens::new::<&str>(&[]).unwrap(),
+                authorize,
+            ));
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+    }
+}
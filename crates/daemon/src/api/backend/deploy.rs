@@ -0,0 +1,124 @@
+use std::io;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::Json;
+use serde::Deserialize;
+use serde_json::json;
+use tempfile::NamedTempFile;
+use tokio::task;
+use tracing::{info, warn};
+
+use crate::api::backend::{put, upload};
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::job::{JobHandle, JobKind};
+use crate::release::Metadata;
+use crate::{AppState, BackendState};
+
+#[derive(Debug, Deserialize)]
+pub struct DeployRequest {
+    upload: String,
+    #[serde(default)]
+    metadata: Option<Metadata>,
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+pub async fn handler(
+    State(app): State<AppState>,
+    state: BackendState,
+    request: Result<Json<DeployRequest>, JsonRejection>,
+) -> ApiResponse {
+    let Json(request) = match request {
+        Ok(request) => request,
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    upload::finish(
+        app,
+        state,
+        request.upload,
+        request.metadata,
+        request.signature,
+        JobKind::Deploy,
+        deploy,
+    )
+    .await
+}
+
+async fn deploy(
+    state: BackendState,
+    temp: NamedTempFile,
+    metadata: Metadata,
+    job: JobHandle,
+) -> ApiResponse {
+    let releases = state.releases();
+    let previous = releases.current();
+    job.step("Phase 1/3: building the new release, the current one keeps serving");
+    let id = match put::release(&state, temp, metadata, &job).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    job.step(format!("Phase 2/3: switching to release {}", id));
+    if let Err(e) = releases.activate(&id) {
+        let msg = format!("Failed to activate release {}: {}", id, e);
+        return ApiResponse::error(ErrorKind::Internal, msg);
+    }
+    let was_running = state.running();
+    if was_running {
+        job.step("Stopping the previous release");
+        if let Err(e) = state.stop().await {
+            let msg = format!("Failed to stop the previous release: {}", e);
+            return ApiResponse::error((&e).into(), msg).with_data(&json!({ "release": id }));
+        }
+    }
+    job.step(format!(
+        "Phase 3/3: starting release {} and waiting until it is healthy",
+        id
+    ));
+    let e = match state.start().await {
+        Ok(_) => {
+            let pruned = {
+                let releases = releases.clone();
+                task::spawn_blocking(move || releases.prune())
+                    .await
+                    .unwrap_or_else(|e| Err(io::Error::other(e)))
+            };
+            if let Err(e) = pruned {
+                warn!("Failed to prune releases: {}", e);
+            }
+            info!("Deployed release {}", id);
+            let data = json!({ "release": id, "previous": previous });
+            return ApiResponse::ok(format!("Deployed release {}", id)).with_data(&data);
+        }
+        Err(e) => e,
+    };
+    let Some(previous) = previous else {
+        let msg = format!("Release {} failed to start: {}", id, e);
+        return ApiResponse::error((&e).into(), msg).with_data(&json!({ "release": id }));
+    };
+    job.step(format!(
+        "Release {} failed to start, rolling back to {}",
+        id, previous
+    ));
+    let data = json!({ "release": id, "rolled_back_to": previous });
+    let rolled_back = match releases.activate(&previous) {
+        Ok(_) if was_running => state.start().await.map(|_| ()),
+        result => result,
+    };
+    match rolled_back {
+        Ok(_) => {
+            let msg = format!(
+                "Release {} failed to start: {}, rolled back to {}",
+                id, e, previous
+            );
+            ApiResponse::error((&e).into(), msg).with_data(&data)
+        }
+        Err(rollback) => {
+            let msg = format!(
+                "Release {} failed to start: {}, and rolling back to {} failed: {}",
+                id, e, previous, rollback
+            );
+            ApiResponse::error((&e).into(), msg).with_data(&data)
+        }
+    }
+}
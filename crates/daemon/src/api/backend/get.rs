@@ -1,44 +1,76 @@
-use std::borrow::Cow;
+use std::fmt;
 
-use axum::extract::State;
-use tracing::{info, warn};
+use serde::Serialize;
 
-use crate::AppState;
+use crate::api::response::ApiResponse;
+use crate::artifact::Artifact;
+use crate::health::Health;
+use crate::release::Metadata;
+use crate::supervisor::Exit;
+use crate::BackendState;
 
-pub async fn handler(State(state): State<AppState>) -> String {
-    let mut backend = state.lock();
-    let commit_info = match backend.commit_info() {
-        Ok((stdout, _)) => {
-            info!("Get commit info from backend successfully");
-            Cow::Owned(stdout)
+#[derive(Debug, Serialize)]
+struct Status {
+    running: bool,
+    health: Health,
+    release: Option<String>,
+    artifact: Option<Artifact>,
+    last_exit: Option<Exit>,
+    metadata: Option<Metadata>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let not_running = "Backend is not running";
+        writeln!(f, "Health: {}", self.health)?;
+        match &self.release {
+            Some(release) => writeln!(f, "Release: {}", release)?,
+            None => writeln!(f, "Release: none")?,
         }
-        Err(e) => {
-            warn!("Failed to get commit info: {}", e);
-            Cow::Borrowed("Failed to get commit info")
+        match &self.metadata {
+            Some(metadata) => writeln!(f, "Commit: {}", metadata)?,
+            None => writeln!(f, "Commit: unknown")?,
         }
-    };
-    let stdout = match backend.stdout() {
-        Ok(stdout) => {
-            info!("Get stdout from backend successfully");
-            stdout
+        match &self.artifact {
+            Some(artifact) => writeln!(f, "Artifact: {}", artifact)?,
+            None => writeln!(f, "Artifact: none")?,
         }
-        Err(e) => {
-            warn!("Failed to get stdout: {}", e);
-            Cow::Borrowed("Failed to get stdout")
+        match &self.last_exit {
+            Some(exit) => writeln!(f, "Last Exit: {}", exit)?,
+            None => writeln!(f, "Last Exit: none")?,
         }
+        write!(
+            f,
+            "\nStandard Output:\n{}\n\nStandard Error:\n{}\n",
+            self.stdout.as_deref().unwrap_or(not_running),
+            self.stderr.as_deref().unwrap_or(not_running)
+        )
+    }
+}
+
+pub async fn handler(state: BackendState) -> ApiResponse {
+    let health = state.check_health().await;
+    let mut backend = state.lock();
+    let releases = backend.releases();
+    let release = releases.current();
+    let status = Status {
+        running: backend.running(),
+        health,
+        metadata: release.as_deref().and_then(|id| releases.metadata(id)),
+        release,
+        artifact: backend.artifact(),
+        last_exit: backend.last_exit(),
+        stdout: backend.stdout(),
+        stderr: backend.stderr(),
     };
-    let stderr = match backend.stderr() {
-        Ok(stderr) => {
-            info!("Get stderr from backend successfully");
-            stderr
-        }
-        Err(e) => {
-            warn!("Failed to get stderr: {}", e);
-            Cow::Borrowed("Failed to get stderr")
-        }
+    let message = if status.running {
+        "Backend is running"
+    } else {
+        "Backend is not running"
     };
-    format!(
-        "Commit Info:\n{}\n\nStandard Output:\n{}\n\nStandard Error:\n{}\n",
-        commit_info, stdout, stderr
-    )
+    ApiResponse::ok(message)
+        .with_text(status.to_string())
+        .with_data(&status)
 }
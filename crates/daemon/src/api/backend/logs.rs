@@ -0,0 +1,110 @@
+use std::convert::Infallible;
+use std::io;
+
+use axum::extract::rejection::QueryRejection;
+use axum::extract::Query;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::stream;
+use serde::Deserialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::output::Filter;
+use crate::BackendState;
+
+#[derive(Debug, Deserialize)]
+pub struct LogQuery {
+    tail: Option<usize>,
+    since: Option<String>,
+    stream: Option<String>,
+}
+
+impl LogQuery {
+    fn filter(self) -> io::Result<Filter> {
+        let since = match self.since {
+            Some(since) => Some(OffsetDateTime::parse(&since, &Rfc3339).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid since {}: {}", since, e),
+                )
+            })?),
+            None => None,
+        };
+        let streams = match self.stream {
+            Some(streams) => streams
+                .split(',')
+                .map(str::parse)
+                .collect::<io::Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        Ok(Filter {
+            streams,
+            since,
+            tail: self.tail,
+        })
+    }
+}
+
+fn filter(query: Result<Query<LogQuery>, QueryRejection>) -> Result<Filter, ApiResponse> {
+    let Query(query) =
+        query.map_err(|e| ApiResponse::error(ErrorKind::BadRequest, e.body_text()))?;
+    query
+        .filter()
+        .map_err(|e| ApiResponse::error((&e).into(), e.to_string()))
+}
+
+pub async fn handler(
+    state: BackendState,
+    query: Result<Query<LogQuery>, QueryRejection>,
+) -> ApiResponse {
+    let filter = match filter(query) {
+        Ok(filter) => filter,
+        Err(response) => return response,
+    };
+    let lines = state.output().lines(&filter);
+    let text = lines
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+    ApiResponse::ok(format!("{} lines", lines.len()))
+        .with_data(&lines)
+        .with_text(text)
+}
+
+pub async fn stream(
+    state: BackendState,
+    query: Result<Query<LogQuery>, QueryRejection>,
+) -> Response {
+    let filter = match filter(query) {
+        Ok(filter) => filter,
+        Err(response) => return response.into_response(),
+    };
+    let receiver = state.output().subscribe();
+    let events = stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        let event = loop {
+            match receiver.recv().await {
+                Ok(line) if !filter.matches(&line) => continue,
+                Ok(line) => {
+                    break Event::default()
+                        .event(line.stream.to_string())
+                        .json_data(&line)
+                        .unwrap_or_else(|e| Event::default().comment(e.to_string()))
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Log stream lagged behind, skipped {} lines", skipped);
+                    break Event::default().event("lagged").data(skipped.to_string());
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        };
+        Some((Ok::<_, Infallible>(event), (receiver, filter)))
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
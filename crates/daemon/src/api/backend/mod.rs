@@ -1,21 +1,63 @@
+pub mod deploy;
 pub mod get;
+pub mod logs;
 pub mod put;
+pub mod releases;
+pub mod restart;
+pub mod rollback;
 pub mod start;
 pub mod stop;
-pub mod restart;
+pub mod upload;
+
+use std::collections::HashMap;
 
+use axum::async_trait;
+use axum::extract::{DefaultBodyLimit, FromRequestParts, Path};
+use axum::http::request::Parts;
 use axum::routing;
 use axum::Router;
 
-use crate::AppState;
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::upload::MAX_CHUNK_SIZE;
+use crate::{AppState, BackendState};
 
-pub const PATH: &str = "/backend";
+pub const PATH: &str = "/backends/:name";
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/", routing::get(get::handler))
         .route("/", routing::put(put::handler))
+        .route("/deploy", routing::post(deploy::handler))
         .route("/start", routing::patch(start::handler))
         .route("/stop", routing::patch(stop::handler))
         .route("/restart", routing::patch(restart::handler))
+        .route("/releases", routing::get(releases::handler))
+        .route("/logs", routing::get(logs::handler))
+        .route("/logs/stream", routing::get(logs::stream))
+        .route("/rollback/:id", routing::patch(rollback::handler))
+        .route("/uploads", routing::post(upload::init))
+        .route("/uploads/:id", routing::get(upload::get))
+        .route(
+            "/uploads/:id/chunks/:index",
+            routing::put(upload::chunk).layer(DefaultBodyLimit::max(MAX_CHUNK_SIZE as usize)),
+        )
+        .route("/uploads/:id/complete", routing::post(upload::complete))
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for BackendState {
+    type Rejection = ApiResponse;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiResponse::error(ErrorKind::BadRequest, e.body_text()))?;
+        let name = params.get("name").map(String::as_str).unwrap_or_default();
+        state.get(name).ok_or_else(|| {
+            ApiResponse::error(ErrorKind::NotFound, format!("Backend {} not found", name))
+        })
+    }
 }
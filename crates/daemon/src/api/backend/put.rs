@@ -1,79 +1,291 @@
-use std::borrow::Cow;
-use std::fs;
-use std::io::Write;
+use std::io;
 
+use axum::extract::multipart::{Field, MultipartRejection};
 use axum::extract::{Multipart, State};
-use tokio::process::Command;
+use axum::http::StatusCode;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::{fs, task};
 use tracing::{info, warn};
 
-use crate::AppState;
+use crate::api::job;
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::archive::{self, EntryError};
+use crate::build;
+use crate::job::{JobHandle, JobKind};
+use crate::release::Metadata;
+use crate::{AppState, BackendState};
 
 const FIELD_NAME: &str = "spring-boot-tar-gz-archive";
+const METADATA_FIELD: &str = "metadata";
+const SHA256_FIELD: &str = "sha256";
+const SIGNATURE_FIELD: &str = "signature";
 
-pub async fn handler(State(state): State<AppState>, mut multipart: Multipart) -> Cow<'static, str> {
-    if state.running() {
-        return Cow::Borrowed("Backend is running, stop it first");
-    }
-    let mut err_msg = Cow::Borrowed("No valid part provided");
-    while let Ok(Some(field)) = multipart.next_field().await {
+pub async fn handler(
+    State(app): State<AppState>,
+    state: BackendState,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> ApiResponse {
+    let mut multipart = match multipart {
+        Ok(multipart) => multipart,
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    let mut error = None;
+    let mut archive = None;
+    let mut metadata = None;
+    let mut expected = None;
+    let mut signature = None;
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                let msg = "Archive exceeds the size limit";
+                return ApiResponse::error(ErrorKind::PayloadTooLarge, msg);
+            }
+            Err(e) => {
+                let msg = format!("Failed to read multipart body: {}", e);
+                return ApiResponse::error(ErrorKind::BadRequest, msg);
+            }
+        };
         match field.name() {
+            Some(METADATA_FIELD) => {
+                let text = match field.text().await {
+                    Ok(text) => text,
+                    Err(e) => {
+                        let msg = format!("Failed to read metadata: {}", e);
+                        return ApiResponse::error(ErrorKind::BadRequest, msg);
+                    }
+                };
+                match serde_json::from_str::<Metadata>(&text) {
+                    Ok(m) => metadata = Some(m),
+                    Err(e) => {
+                        let msg = format!("Invalid metadata: {}", e);
+                        return ApiResponse::error(ErrorKind::BadRequest, msg);
+                    }
+                }
+            }
+            Some(SHA256_FIELD) => match field.text().await {
+                Ok(text) => expected = Some(text.trim().to_ascii_lowercase()),
+                Err(e) => {
+                    let msg = format!("Failed to read SHA-256: {}", e);
+                    return ApiResponse::error(ErrorKind::BadRequest, msg);
+                }
+            },
+            Some(SIGNATURE_FIELD) => match field.text().await {
+                Ok(text) => signature = Some(text),
+                Err(e) => {
+                    let msg = format!("Failed to read signature: {}", e);
+                    return ApiResponse::error(ErrorKind::BadRequest, msg);
+                }
+            },
             Some(FIELD_NAME) => {
                 info!("Creating temp file");
-                let mut temp = match tempfile::NamedTempFile::new() {
+                let temp = match NamedTempFile::new() {
                     Ok(t) => t,
                     Err(e) => {
-                        err_msg = Cow::Owned(format!("Failed to create temp file: {}", e));
-                        warn!("{}", &err_msg);
+                        let msg = format!("Failed to create temp file: {}", e);
+                        error = Some(ApiResponse::error(ErrorKind::Internal, msg));
                         continue;
                     }
                 };
-                info!("Reading uploaded bytes");
-                let bytes = match field.bytes().await {
-                    Ok(b) => b,
+                let mut file = match temp.as_file().try_clone() {
+                    Ok(f) => File::from_std(f),
                     Err(e) => {
-                        err_msg = Cow::Owned(format!("Failed to read bytes from field: {}", e));
-                        warn!("{}", &err_msg);
+                        let msg = format!("Failed to open temp file: {}", e);
+                        error = Some(ApiResponse::error(ErrorKind::Internal, msg));
                         continue;
                     }
                 };
-                info!("Writing {} bytes to temp file", bytes.len());
-                if let Err(e) = temp.write_all(bytes.as_ref()) {
-                    err_msg = Cow::Owned(format!("Failed to write bytes to temp file: {}", e));
-                    warn!("{}", &err_msg);
-                    continue;
-                }
-                let temp_path = temp.path();
-                let backend_path = state.path();
-                info!("Deleting backend directory");
-                if let Err(e) = Command::new("rm")
-                    .arg("-rf")
-                    .arg(&backend_path)
-                    .output()
-                    .await
-                {
-                    err_msg = Cow::Owned(format!("Failed to delete backend directory: {}", e));
-                    warn!("{}", &err_msg);
-                    continue;
-                }
-                info!("Creating backend directory");
-                fs::create_dir(&backend_path).unwrap();
-                info!("Extracting file to {}", &backend_path.display());
-                if let Err(e) = Command::new("tar")
-                    .arg("-xf")
-                    .arg(temp_path)
-                    .arg("-C")
-                    .arg(backend_path)
-                    .output()
-                    .await
-                {
-                    err_msg = Cow::Owned(format!("Failed to extract file: {}", e));
-                    warn!("{}", &err_msg);
-                    continue;
-                }
-                return Cow::Borrowed("File uploaded successfully");
+                info!("Streaming uploaded bytes to temp file");
+                let mut hasher = Sha256::new();
+                let size = match save(&mut field, &mut file, &mut hasher).await {
+                    Ok(size) => size,
+                    Err((ErrorKind::PayloadTooLarge, msg)) => {
+                        return ApiResponse::error(ErrorKind::PayloadTooLarge, msg);
+                    }
+                    Err((kind, msg)) => {
+                        error = Some(ApiResponse::error(kind, msg));
+                        continue;
+                    }
+                };
+                let sha256 = hex::encode(hasher.finalize());
+                info!("Wrote {} bytes with SHA-256 {} to temp file", size, sha256);
+                archive = Some((temp, sha256));
             }
             invalid => warn!("Invalid field name: {:?}", invalid),
         }
     }
-    err_msg
+    let Some((temp, sha256)) = archive else {
+        return error.unwrap_or_else(|| {
+            ApiResponse::error(ErrorKind::BadRequest, "No valid part provided")
+        });
+    };
+    match expected {
+        Some(expected) if expected != sha256 => {
+            let msg = format!(
+                "Archive has SHA-256 {}, expected {}, it was corrupted in transit",
+                sha256, expected
+            );
+            return ApiResponse::error(ErrorKind::ChecksumMismatch, msg);
+        }
+        Some(_) => info!("Verified SHA-256 {} of the archive", sha256),
+        None => warn!("No SHA-256 sent with the archive, it cannot be verified"),
+    }
+    let metadata = match verify(&app, metadata, sha256, signature.as_deref()) {
+        Ok(metadata) => metadata,
+        Err(response) => return response,
+    };
+    job::accepted(app.jobs().spawn(JobKind::Update, state.name(), |job| {
+        update(state, temp, metadata, job)
+    }))
+}
+
+pub fn verify(
+    app: &AppState,
+    metadata: Option<Metadata>,
+    sha256: String,
+    signature: Option<&str>,
+) -> Result<Metadata, ApiResponse> {
+    let signer = app.signers().verify(&sha256, signature).map_err(|e| {
+        let kind = match e.kind() {
+            io::ErrorKind::PermissionDenied => ErrorKind::Forbidden,
+            _ => (&e).into(),
+        };
+        ApiResponse::error(kind, format!("Failed to verify the archive: {}", e))
+    })?;
+    Ok(Metadata {
+        archive_sha256: Some(sha256),
+        signer,
+        ..metadata.unwrap_or_default()
+    })
+}
+
+pub async fn update(
+    state: BackendState,
+    temp: NamedTempFile,
+    metadata: Metadata,
+    job: JobHandle,
+) -> ApiResponse {
+    let id = match release(&state, temp, metadata, &job).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let releases = state.releases();
+    job.step(format!("Activating release {}", id));
+    if let Err(e) = releases.activate(&id) {
+        let msg = format!("Failed to activate release {}: {}", id, e);
+        return ApiResponse::error(ErrorKind::Internal, msg);
+    }
+    let pruned = {
+        let releases = releases.clone();
+        task::spawn_blocking(move || releases.prune())
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e)))
+    };
+    if let Err(e) = pruned {
+        warn!("Failed to prune releases: {}", e);
+    }
+    let data = json!({ "release": id });
+    if state.running() {
+        job.step(format!("Restarting backend with release {}", id));
+        if let Err(e) = state.restart().await {
+            let msg = format!(
+                "Release {} activated, but failed to restart backend: {}",
+                id, e
+            );
+            return ApiResponse::error((&e).into(), msg).with_data(&data);
+        }
+    }
+    let msg = format!("File uploaded successfully as release {}", id);
+    ApiResponse::ok(msg).with_data(&data)
+}
+
+pub async fn release(
+    state: &BackendState,
+    temp: NamedTempFile,
+    metadata: Metadata,
+    job: &JobHandle,
+) -> Result<String, ApiResponse> {
+    let releases = state.releases();
+    job.step("Creating release directory");
+    let release_path = match releases.create() {
+        Ok(p) => p,
+        Err(e) => {
+            let msg = format!("Failed to create release directory: {}", e);
+            return Err(ApiResponse::error(ErrorKind::Internal, msg));
+        }
+    };
+    job.step(format!("Extracting archive to {}", release_path.display()));
+    let archive = temp.path().to_path_buf();
+    let dest = release_path.clone();
+    let extracted = task::spawn_blocking(move || archive::extract(archive, dest))
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)));
+    let failures = match extracted {
+        Ok(failures) => failures,
+        Err(e) => vec![EntryError {
+            path: temp.path().to_path_buf(),
+            reason: e.to_string(),
+        }],
+    };
+    if !failures.is_empty() {
+        let mut msg = String::from("Failed to extract file:");
+        for failure in &failures {
+            msg.push_str(&format!("\n  {}", failure));
+        }
+        if let Err(e) = fs::remove_dir_all(&release_path).await {
+            warn!("Failed to remove release directory: {}", e);
+        }
+        return Err(ApiResponse::error(ErrorKind::ExtractionFailed, msg).with_data(&failures));
+    }
+    job.step("Building release");
+    if let Err(e) = build::build(&release_path, &state.build_config(), &state.output()).await {
+        if let Err(e) = fs::remove_dir_all(&release_path).await {
+            warn!("Failed to remove release directory: {}", e);
+        }
+        let msg = format!("Failed to build backend: {}", e);
+        return Err(ApiResponse::error(ErrorKind::BuildFailed, msg));
+    }
+    releases.add(&release_path, Some(&metadata)).map_err(|e| {
+        let msg = format!("Failed to add release: {}", e);
+        ApiResponse::error(ErrorKind::Internal, msg)
+    })
+}
+
+async fn save(
+    field: &mut Field<'_>,
+    file: &mut File,
+    hasher: &mut Sha256,
+) -> Result<usize, (ErrorKind, String)> {
+    let mut size = 0;
+    loop {
+        match field.chunk().await {
+            Ok(Some(chunk)) => {
+                size += chunk.len();
+                hasher.update(&chunk);
+                if let Err(e) = file.write_all(&chunk).await {
+                    let msg = format!("Failed to write bytes to temp file: {}", e);
+                    return Err((ErrorKind::Internal, msg));
+                }
+            }
+            Ok(None) => break,
+            Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                let msg = String::from("Archive exceeds the size limit");
+                return Err((ErrorKind::PayloadTooLarge, msg));
+            }
+            Err(e) => {
+                let msg = format!("Failed to read bytes from field: {}", e);
+                return Err((ErrorKind::BadRequest, msg));
+            }
+        }
+    }
+    if let Err(e) = file.flush().await {
+        let msg = format!("Failed to flush temp file: {}", e);
+        return Err((ErrorKind::Internal, msg));
+    }
+    Ok(size)
 }
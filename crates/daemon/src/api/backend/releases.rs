@@ -0,0 +1,24 @@
+use tracing::info;
+
+use crate::api::response::ApiResponse;
+use crate::BackendState;
+
+pub async fn handler(state: BackendState) -> ApiResponse {
+    match state.releases().list() {
+        Ok(releases) => {
+            info!("Listed {} releases", releases.len());
+            if releases.is_empty() {
+                return ApiResponse::ok("No release deployed").with_data(&releases);
+            }
+            let mut text = String::new();
+            for release in &releases {
+                let marker = if release.active { '*' } else { ' ' };
+                text.push_str(&format!("{} {}\n", marker, release.id));
+            }
+            ApiResponse::ok(format!("{} releases deployed", releases.len()))
+                .with_text(text)
+                .with_data(&releases)
+        }
+        Err(e) => ApiResponse::error((&e).into(), format!("Failed to list releases: {}", e)),
+    }
+}
@@ -1,12 +1,21 @@
-use std::borrow::Cow;
-
 use axum::extract::State;
 
-use crate::AppState;
+use crate::api::job;
+use crate::api::response::ApiResponse;
+use crate::job::JobKind;
+use crate::{AppState, BackendState};
 
-pub async fn handler(State(state): State<AppState>) -> Cow<'static, str> {
-    match state.restart() {
-        Ok(_) => Cow::Borrowed("Backend restarted\n"),
-        Err(e) => Cow::Owned(format!("Failed to restart backend: {}\n", e)),
-    }
+pub async fn handler(State(app): State<AppState>, state: BackendState) -> ApiResponse {
+    job::accepted(
+        app.jobs()
+            .spawn(JobKind::Restart, state.name(), |job| async move {
+                job.step("Restarting backend");
+                match state.restart().await {
+                    Ok(_) => ApiResponse::ok("Backend restarted"),
+                    Err(e) => {
+                        ApiResponse::error((&e).into(), format!("Failed to restart backend: {}", e))
+                    }
+                }
+            }),
+    )
 }
@@ -0,0 +1,31 @@
+use axum::extract::{Path, State};
+use tracing::info;
+
+use crate::api::job;
+use crate::api::response::ApiResponse;
+use crate::job::JobKind;
+use crate::{AppState, BackendState};
+
+pub async fn handler(
+    State(app): State<AppState>,
+    state: BackendState,
+    Path((_, id)): Path<(String, String)>,
+) -> ApiResponse {
+    job::accepted(
+        app.jobs()
+            .spawn(JobKind::Rollback, state.name(), |job| async move {
+                job.step(format!("Rolling back to release {}", id));
+                match state.rollback(&id).await {
+                    Ok(release) => {
+                        info!("Backend rolled back to {}", release.id);
+                        ApiResponse::ok(format!("Backend rolled back to {}", release.id))
+                            .with_data(&release)
+                    }
+                    Err(e) => ApiResponse::error(
+                        (&e).into(),
+                        format!("Failed to roll back backend: {}", e),
+                    ),
+                }
+            }),
+    )
+}
@@ -1,20 +1,28 @@
-use std::borrow::Cow;
-
 use axum::extract::State;
-use tracing::{info, warn};
+use tracing::info;
 
-use crate::AppState;
+use crate::api::job;
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::job::JobKind;
+use crate::{AppState, BackendState};
 
-pub async fn handler(State(state): State<AppState>) -> Cow<'static, str> {
-    match state.start() {
-        Ok(_) => {
-            info!("Backend started");
-            Cow::Borrowed("Backend started\n")
-        }
-        Err(e) => {
-            let msg = format!("Failed to start backend: {}\n", e);
-            warn!("{}", &msg);
-            Cow::Owned(msg)
-        }
-    }
+pub async fn handler(State(app): State<AppState>, state: BackendState) -> ApiResponse {
+    job::accepted(
+        app.jobs()
+            .spawn(JobKind::Start, state.name(), |job| async move {
+                job.step("Starting backend");
+                match state.start().await {
+                    Ok(true) => {
+                        info!("Backend started");
+                        ApiResponse::ok("Backend started")
+                    }
+                    Ok(false) => {
+                        ApiResponse::error(ErrorKind::AlreadyRunning, "Backend is already running")
+                    }
+                    Err(e) => {
+                        ApiResponse::error((&e).into(), format!("Failed to start backend: {}", e))
+                    }
+                }
+            }),
+    )
 }
@@ -1,20 +1,17 @@
-use std::borrow::Cow;
+use tracing::info;
 
-use axum::extract::State;
-use tracing::{info, warn};
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::BackendState;
 
-use crate::AppState;
-
-pub async fn handler(State(state): State<AppState>) -> Cow<'static, str> {
-    match state.stop() {
-        Ok(s) => {
+pub async fn handler(state: BackendState) -> ApiResponse {
+    match state.stop().await {
+        Ok(Some(stopped)) => {
             info!("Backend stopped");
-            s
-        }
-        Err(e) => {
-            let msg = format!("Failed to stop backend: {}\n", e);
-            warn!("{}", &msg);
-            Cow::Owned(msg)
+            ApiResponse::ok(stopped.summary())
+                .with_text(stopped.to_string())
+                .with_data(&stopped)
         }
+        Ok(None) => ApiResponse::error(ErrorKind::NotRunning, "Backend is not running"),
+        Err(e) => ApiResponse::error((&e).into(), format!("Failed to stop backend: {}", e)),
     }
 }
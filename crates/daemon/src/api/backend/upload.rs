@@ -0,0 +1,187 @@
+use std::future::Future;
+use std::io;
+
+use axum::body::Bytes;
+use axum::extract::rejection::{BytesRejection, JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+use tempfile::NamedTempFile;
+use tokio::task;
+use tracing::warn;
+
+use crate::api::backend::put;
+use crate::api::job;
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::job::{JobHandle, JobKind};
+use crate::release::Metadata;
+use crate::upload::{Upload, MAX_CHUNK_SIZE};
+use crate::{AppState, BackendState};
+
+#[derive(Debug, Deserialize)]
+pub struct InitRequest {
+    size: u64,
+    sha256: String,
+    chunk_size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChunkQuery {
+    sha256: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CompleteRequest {
+    metadata: Option<Metadata>,
+    signature: Option<String>,
+}
+
+pub async fn init(
+    State(app): State<AppState>,
+    state: BackendState,
+    request: Result<Json<InitRequest>, JsonRejection>,
+) -> ApiResponse {
+    let Json(request) = match request {
+        Ok(request) => request,
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    if request.size > app.max_archive_size() as u64 {
+        return ApiResponse::error(ErrorKind::PayloadTooLarge, "Archive exceeds the size limit");
+    }
+    let uploads = state.uploads();
+    let upload = task::spawn_blocking(move || {
+        uploads.init(request.size, &request.sha256, request.chunk_size)
+    })
+    .await
+    .unwrap_or_else(|e| Err(io::Error::other(e)));
+    respond(upload, "Failed to start upload")
+}
+
+pub async fn get(state: BackendState, Path((_, id)): Path<(String, String)>) -> ApiResponse {
+    let uploads = state.uploads();
+    let upload = task::spawn_blocking(move || uploads.get(&id))
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)));
+    respond(upload, "Failed to get upload")
+}
+
+pub async fn chunk(
+    state: BackendState,
+    path: Result<Path<(String, String, u64)>, PathRejection>,
+    query: Result<Query<ChunkQuery>, QueryRejection>,
+    body: Result<Bytes, BytesRejection>,
+) -> ApiResponse {
+    let Path((_, id, index)) = match path {
+        Ok(path) => path,
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            let msg = format!("Chunk exceeds the limit of {} bytes", MAX_CHUNK_SIZE);
+            return ApiResponse::error(ErrorKind::PayloadTooLarge, msg);
+        }
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    let uploads = state.uploads();
+    let upload = task::spawn_blocking(move || uploads.write(&id, index, &query.sha256, &body))
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)));
+    respond(upload, "Failed to write chunk")
+}
+
+pub async fn complete(
+    State(app): State<AppState>,
+    state: BackendState,
+    Path((_, id)): Path<(String, String)>,
+    request: Result<Json<CompleteRequest>, JsonRejection>,
+) -> ApiResponse {
+    let Json(request) = match request {
+        Ok(request) => request,
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    finish(
+        app,
+        state,
+        id,
+        request.metadata,
+        request.signature,
+        JobKind::Update,
+        put::update,
+    )
+    .await
+}
+
+pub async fn finish<F, Fut>(
+    app: AppState,
+    state: BackendState,
+    id: String,
+    metadata: Option<Metadata>,
+    signature: Option<String>,
+    kind: JobKind,
+    run: F,
+) -> ApiResponse
+where
+    F: FnOnce(BackendState, NamedTempFile, Metadata, JobHandle) -> Fut,
+    Fut: Future<Output = ApiResponse> + Send + 'static,
+{
+    let uploads = state.uploads();
+    let assembled = {
+        let id = id.clone();
+        task::spawn_blocking(move || uploads.assemble(&id))
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e)))
+    };
+    let (upload, temp) = match assembled {
+        Ok(assembled) => assembled,
+        Err(e) => {
+            return ApiResponse::error(error_kind(&e), format!("Failed to complete upload: {}", e))
+        }
+    };
+    let metadata = match put::verify(&app, metadata, upload.sha256, signature.as_deref()) {
+        Ok(metadata) => metadata,
+        Err(response) => return response,
+    };
+    let uploads = state.uploads();
+    let job = app
+        .jobs()
+        .spawn(kind, state.name(), |job| run(state, temp, metadata, job));
+    if job.is_ok() {
+        let removed = {
+            let id = id.clone();
+            task::spawn_blocking(move || uploads.remove(&id))
+                .await
+                .unwrap_or_else(|e| Err(io::Error::other(e)))
+        };
+        if let Err(e) = removed {
+            warn!("Failed to remove upload {}: {}", id, e);
+        }
+    }
+    job::accepted(job)
+}
+
+fn respond(upload: io::Result<Upload>, context: &str) -> ApiResponse {
+    match upload {
+        Ok(upload) => ApiResponse::ok(format!(
+            "Upload {}: {} of {} chunks received",
+            upload.id,
+            upload.received.len(),
+            upload.chunks
+        ))
+        .with_data(&upload),
+        Err(e) => ApiResponse::error(error_kind(&e), format!("{}: {}", context, e)),
+    }
+}
+
+fn error_kind(e: &io::Error) -> ErrorKind {
+    match e.kind() {
+        io::ErrorKind::InvalidData => ErrorKind::ChecksumMismatch,
+        _ => e.into(),
+    }
+}
@@ -0,0 +1,54 @@
+use std::io;
+
+use axum::extract::rejection::PathRejection;
+use axum::extract::{Path, State};
+use axum::routing;
+use axum::Router;
+
+use crate::api::response::{ApiResponse, ErrorKind};
+use crate::job::Job;
+use crate::AppState;
+
+pub const PATH: &str = "/jobs";
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", routing::get(list))
+        .route("/:id", routing::get(get))
+}
+
+pub fn accepted(job: io::Result<Job>) -> ApiResponse {
+    match job {
+        Ok(job) => ApiResponse::accepted(format!(
+            "Started {} job {} for backend {}",
+            job.kind, job.id, job.backend
+        ))
+        .with_data(&job),
+        Err(e) => ApiResponse::error((&e).into(), format!("Failed to start job: {}", e)),
+    }
+}
+
+async fn list(State(state): State<AppState>) -> ApiResponse {
+    let jobs = state.jobs().list();
+    let text = jobs
+        .iter()
+        .map(|job| format!("{} ({} {}): {}", job.id, job.kind, job.backend, job.status))
+        .collect::<Vec<_>>()
+        .join("\n");
+    ApiResponse::ok(format!("{} jobs", jobs.len()))
+        .with_data(&jobs)
+        .with_text(text)
+}
+
+async fn get(State(state): State<AppState>, id: Result<Path<u64>, PathRejection>) -> ApiResponse {
+    let Path(id) = match id {
+        Ok(id) => id,
+        Err(e) => return ApiResponse::error(ErrorKind::BadRequest, e.body_text()),
+    };
+    match state.jobs().get(id) {
+        Some(job) => ApiResponse::ok(format!("Job {} {}", job.id, job.status))
+            .with_text(job.to_string())
+            .with_data(&job),
+        None => ApiResponse::error(ErrorKind::NotFound, format!("Job {} not found", id)),
+    }
+}
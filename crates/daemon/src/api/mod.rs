@@ -1 +1,4 @@
+pub mod auth;
 pub mod backend;
+pub mod job;
+pub mod response;
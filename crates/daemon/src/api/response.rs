@@ -0,0 +1,196 @@
+use std::borrow::Cow;
+use std::io;
+
+use axum::body;
+use axum::extract::Request;
+use axum::http::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    AlreadyRunning,
+    NotRunning,
+    Busy,
+    PayloadTooLarge,
+    ChecksumMismatch,
+    ExtractionFailed,
+    BuildFailed,
+    Unhealthy,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn status_code(self) -> StatusCode {
+        match self {
+            ErrorKind::BadRequest | ErrorKind::ChecksumMismatch => StatusCode::BAD_REQUEST,
+            ErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::AlreadyRunning | ErrorKind::NotRunning | ErrorKind::Busy => {
+                StatusCode::CONFLICT
+            }
+            ErrorKind::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorKind::ExtractionFailed => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorKind::BuildFailed | ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorKind::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+impl From<&io::Error> for ErrorKind {
+    fn from(e: &io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            io::ErrorKind::InvalidInput => ErrorKind::BadRequest,
+            io::ErrorKind::TimedOut => ErrorKind::Unhealthy,
+            io::ErrorKind::ResourceBusy => ErrorKind::Busy,
+            _ => ErrorKind::Internal,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Body {
+    status: Status,
+    message: Cow<'static, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorKind>,
+    timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+struct Text(String);
+
+#[derive(Debug)]
+pub struct ApiResponse {
+    code: StatusCode,
+    body: Body,
+    text: Option<String>,
+}
+
+impl ApiResponse {
+    pub fn ok<M: Into<Cow<'static, str>>>(message: M) -> Self {
+        Self::new(StatusCode::OK, Status::Ok, None, message.into())
+    }
+
+    pub fn accepted<M: Into<Cow<'static, str>>>(message: M) -> Self {
+        Self::new(StatusCode::ACCEPTED, Status::Ok, None, message.into())
+    }
+
+    pub fn error<M: Into<Cow<'static, str>>>(kind: ErrorKind, message: M) -> Self {
+        let message = message.into();
+        warn!("{}", message);
+        Self::new(kind.status_code(), Status::Error, Some(kind), message)
+    }
+
+    fn new(
+        code: StatusCode,
+        status: Status,
+        error: Option<ErrorKind>,
+        message: Cow<'static, str>,
+    ) -> Self {
+        Self {
+            code,
+            body: Body {
+                status,
+                message,
+                error,
+                timestamp: OffsetDateTime::now_utc()
+                    .format(&Rfc3339)
+                    .unwrap_or_default(),
+                data: None,
+            },
+            text: None,
+        }
+    }
+
+    pub fn with_data<T: Serialize>(mut self, data: &T) -> Self {
+        match serde_json::to_value(data) {
+            Ok(value) => self.body.data = Some(value),
+            Err(e) => warn!("Failed to serialize response data: {}", e),
+        }
+        self
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.body.status == Status::Ok
+    }
+
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::to_value(&self.body).unwrap_or_default()
+    }
+
+    pub fn with_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+}
+
+impl IntoResponse for ApiResponse {
+    fn into_response(self) -> Response {
+        let mut text = self.text.unwrap_or_else(|| self.body.message.to_string());
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let mut response = (self.code, Json(self.body)).into_response();
+        response.extensions_mut().insert(Text(text));
+        response
+    }
+}
+
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    let text = prefers_text(request.headers());
+    let mut response = next.run(request).await;
+    if !text {
+        return response;
+    }
+    match response.extensions_mut().remove::<Text>() {
+        Some(Text(text)) => {
+            let (mut parts, _) = response.into_parts();
+            parts.headers.remove(CONTENT_LENGTH);
+            parts.headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; charset=utf-8"),
+            );
+            Response::from_parts(parts, body::Body::from(text))
+        }
+        None => response,
+    }
+}
+
+fn prefers_text(headers: &HeaderMap) -> bool {
+    let accept = headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let text = accept.find("text/plain");
+    let json = accept.find("application/json");
+    match (text, json) {
+        (Some(text), Some(json)) => text < json,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+use std::fs::{read_link, remove_file, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use tar::{Archive, EntryType};
+use tracing::{info, warn};
+
+const MAX_LINK_DEPTH: usize = 40;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for EntryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.reason)
+    }
+}
+
+pub fn extract<P: AsRef<Path>, Q: AsRef<Path>>(archive: P, dest: Q) -> io::Result<Vec<EntryError>> {
+    let dest = dest.as_ref();
+    let mut archive = Archive::new(GzDecoder::new(File::open(archive)?));
+    archive.set_preserve_permissions(true);
+    let mut errors = Vec::new();
+    let mut count = 0;
+    let mut links = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let reject = |reason: &str| {
+            warn!("Rejected entry {}: {}", path.display(), reason);
+            EntryError {
+                path: path.clone(),
+                reason: reason.to_string(),
+            }
+        };
+        let Some(normalized) = normalize(&path) else {
+            errors.push(reject("path escapes the working directory"));
+            continue;
+        };
+        let parent = normalized.parent().unwrap_or(Path::new(""));
+        if resolve(dest, parent).is_none() {
+            errors.push(reject("path escapes the working directory through a link"));
+            continue;
+        }
+        let link = match entry.header().entry_type() {
+            EntryType::Symlink => entry.link_name()?.map(|link| parent.join(link)),
+            EntryType::Link => entry.link_name()?.map(|link| link.into_owned()),
+            _ => None,
+        };
+        if let Some(link) = &link {
+            if resolve(dest, link).is_none() {
+                errors.push(reject("link escapes the working directory"));
+                continue;
+            }
+        }
+        match entry.unpack_in(dest) {
+            Ok(true) => {
+                count += 1;
+                if link.is_some() {
+                    links.push(normalized);
+                }
+            }
+            Ok(false) => errors.push(reject("path escapes the working directory")),
+            Err(e) => errors.push(reject(&e.to_string())),
+        }
+    }
+    let mut removed = true;
+    while removed {
+        removed = false;
+        let mut kept = Vec::with_capacity(links.len());
+        for path in links.drain(..) {
+            if resolve(dest, &path).is_some() {
+                kept.push(path);
+                continue;
+            }
+            warn!(
+                "Removing link {}: it escapes the working directory",
+                path.display()
+            );
+            remove_file(dest.join(&path))?;
+            count -= 1;
+            removed = true;
+            errors.push(EntryError {
+                path,
+                reason: String::from("link escapes the working directory"),
+            });
+        }
+        links = kept;
+    }
+    info!(
+        "Extracted {} entries to {}, {} failed",
+        count,
+        dest.display(),
+        errors.len()
+    );
+    Ok(errors)
+}
+
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+fn resolve(dest: &Path, path: &Path) -> Option<PathBuf> {
+    resolve_from(dest, PathBuf::new(), path, 0)
+}
+
+fn resolve_from(dest: &Path, mut resolved: PathBuf, path: &Path, depth: usize) -> Option<PathBuf> {
+    if depth > MAX_LINK_DEPTH {
+        return None;
+    }
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                resolved.push(part);
+                if let Ok(target) = read_link(dest.join(&resolved)) {
+                    resolved.pop();
+                    resolved = resolve_from(dest, resolved, &target, depth + 1)?;
+                }
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir, symlink_metadata};
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tar::{Builder, Header};
+    use tempfile::{tempdir, NamedTempFile, TempDir};
+
+    use super::*;
+
+    fn archive(entries: &[(&str, EntryType, &str)]) -> NamedTempFile {
+        let temp = NamedTempFile::new().unwrap();
+        let mut builder = Builder::new(GzEncoder::new(temp.reopen().unwrap(), Compression::fast()));
+        for (path, kind, link) in entries {
+            let mut header = Header::new_gnu();
+            let old = header.as_old_mut();
+            old.name[..path.len()].copy_from_slice(path.as_bytes());
+            old.linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(*kind);
+            header.set_mode(if kind.is_dir() { 0o755 } else { 0o644 });
+            let data: &[u8] = if kind.is_file() { b"data" } else { b"" };
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        temp
+    }
+
+    fn extract_into(entries: &[(&str, EntryType, &str)]) -> (TempDir, Vec<EntryError>) {
+        let root = tempdir().unwrap();
+        let dest = root.path().join("release");
+        create_dir(&dest).unwrap();
+        let errors = extract(archive(entries).path(), &dest).unwrap();
+        (root, errors)
+    }
+
+    fn rejected(errors: &[EntryError]) -> Vec<String> {
+        errors
+            .iter()
+            .map(|error| error.path.display().to_string())
+            .collect()
+    }
+
+    fn exists(root: &TempDir, path: &str) -> bool {
+        symlink_metadata(root.path().join(path)).is_ok()
+    }
+
+    #[test]
+    fn extracts_regular_entries_and_inner_links() {
+        let (root, errors) = extract_into(&[
+            ("src", EntryType::Directory, ""),
+            ("src/main.rs", EntryType::Regular, ""),
+            ("main.rs", EntryType::Symlink, "src/main.rs"),
+            ("src/self", EntryType::Symlink, "../src"),
+            ("copy.rs", EntryType::Link, "src/main.rs"),
+        ]);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(exists(&root, "release/src/main.rs"));
+        assert!(exists(&root, "release/main.rs"));
+        assert!(exists(&root, "release/src/self"));
+        assert!(exists(&root, "release/copy.rs"));
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let (root, errors) = extract_into(&[
+            ("/absolute", EntryType::Regular, ""),
+            ("kept", EntryType::Regular, ""),
+        ]);
+        assert_eq!(rejected(&errors), ["/absolute"]);
+        assert!(exists(&root, "release/kept"));
+    }
+
+    #[test]
+    fn rejects_parent_dir_entries() {
+        let (root, errors) = extract_into(&[
+            ("../escape", EntryType::Regular, ""),
+            ("dir/../../escape", EntryType::Regular, ""),
+        ]);
+        assert_eq!(rejected(&errors), ["../escape", "dir/../../escape"]);
+        assert!(!exists(&root, "escape"));
+    }
+
+    #[test]
+    fn rejects_links_escaping_directly() {
+        let (root, errors) = extract_into(&[
+            ("up", EntryType::Symlink, ".."),
+            ("dir", EntryType::Directory, ""),
+            ("dir/up", EntryType::Symlink, "../../outside"),
+            ("absolute", EntryType::Symlink, "/etc"),
+            ("hard", EntryType::Link, "../outside"),
+        ]);
+        assert_eq!(rejected(&errors), ["up", "dir/up", "absolute", "hard"]);
+        assert!(!exists(&root, "release/up"));
+        assert!(!exists(&root, "release/dir/up"));
+        assert!(!exists(&root, "release/absolute"));
+        assert!(!exists(&root, "release/hard"));
+    }
+
+    #[test]
+    fn rejects_links_escaping_through_other_links() {
+        let (root, errors) = extract_into(&[
+            ("x", EntryType::Directory, ""),
+            ("x/y", EntryType::Symlink, ".."),
+            ("x/y/z", EntryType::Symlink, ".."),
+            ("here", EntryType::Symlink, "."),
+            ("there", EntryType::Symlink, "here/.."),
+            ("x/y/file", EntryType::Regular, ""),
+        ]);
+        assert_eq!(rejected(&errors), ["x/y/z", "there"]);
+        assert!(!exists(&root, "release/z"));
+        assert!(!exists(&root, "release/there"));
+        assert!(exists(&root, "release/file"));
+    }
+
+    #[test]
+    fn removes_links_that_escape_after_later_entries() {
+        let (root, errors) = extract_into(&[
+            ("late", EntryType::Symlink, "dir/sub/../.."),
+            ("dir", EntryType::Symlink, "."),
+        ]);
+        assert_eq!(rejected(&errors), ["late"]);
+        assert!(!exists(&root, "release/late"));
+        assert!(exists(&root, "release/dir"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{canonicalize, read_to_string, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::config::{BuildConfig, BuildTool};
+
+const POM: &str = "pom.xml";
+const EXCLUDED_SUFFIXES: [&str; 4] = ["-plain.jar", "-sources.jar", "-javadoc.jar", "-tests.jar"];
+const MAX_INTERPOLATIONS: usize = 32;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Artifact {
+    pub fn inspect(path: PathBuf) -> io::Result<Self> {
+        let mut file = File::open(&path)?;
+        let mut hasher = Sha256::new();
+        let size = io::copy(&mut file, &mut hasher)?;
+        Ok(Self {
+            path,
+            size,
+            sha256: hex::encode(hasher.finalize()),
+        })
+    }
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes, sha256 {})",
+            self.path.display(),
+            self.size,
+            self.sha256
+        )
+    }
+}
+
+pub fn resolve<P: AsRef<Path>>(path: P, config: &BuildConfig) -> io::Result<PathBuf> {
+    let path = path.as_ref();
+    let artifact = match (&config.artifact, &config.tool) {
+        (Some(pattern), _) => find(path, pattern, false)?,
+        (None, BuildTool::Maven { .. }) if path.join(POM).is_file() => from_pom(path)?,
+        (None, BuildTool::Maven { .. }) => find(path, "target/*.jar", true)?,
+        (None, BuildTool::Gradle { .. }) => find(path, "build/libs/*.jar", true)?,
+        (None, BuildTool::Shell { .. }) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No artifact pattern configured for the shell build",
+            ));
+        }
+    };
+    let artifact = canonicalize(artifact)?;
+    info!("Found artifact: {}", artifact.display());
+    Ok(artifact)
+}
+
+fn find(path: &Path, pattern: &str, exclude: bool) -> io::Result<PathBuf> {
+    let full_pattern = path.join(pattern);
+    let mut matches = glob::glob(&full_pattern.to_string_lossy())
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid artifact pattern {}: {}", pattern, e),
+            )
+        })?
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let name = path.to_string_lossy();
+            !exclude
+                || !EXCLUDED_SUFFIXES
+                    .iter()
+                    .any(|suffix| name.ends_with(suffix))
+        })
+        .collect::<Vec<_>>();
+    matches.sort();
+    match matches.len() {
+        0 => {
+            warn!("No artifact matches {}", pattern);
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No artifact matches {}", pattern),
+            ))
+        }
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let candidates = matches
+                .iter()
+                .filter_map(|candidate| candidate.strip_prefix(path).ok())
+                .map(|candidate| candidate.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            warn!("Ambiguous artifact pattern {}: {}", pattern, candidates);
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Artifact pattern {} is ambiguous, it matches {}",
+                    pattern, candidates
+                ),
+            ))
+        }
+    }
+}
+
+fn from_pom(path: &Path) -> io::Result<PathBuf> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let content = read_to_string(path.join(POM))?;
+    let document =
+        Document::parse(&content).map_err(|e| invalid(format!("Invalid {}: {}", POM, e)))?;
+    let project = document.root_element();
+    let parent = child(project, "parent");
+    let artifact_id = text(Some(project), "artifactId")
+        .ok_or_else(|| invalid(format!("No artifactId in {}", POM)))?;
+    let version = text(Some(project), "version")
+        .or_else(|| text(parent, "version"))
+        .ok_or_else(|| invalid(format!("No version in {}", POM)))?;
+    let packaging = text(Some(project), "packaging").unwrap_or_else(|| String::from("jar"));
+    if packaging != "jar" && packaging != "war" {
+        return Err(invalid(format!(
+            "Packaging {} does not produce a runnable artifact",
+            packaging
+        )));
+    }
+    let mut properties = HashMap::new();
+    if let Some(node) = child(project, "properties") {
+        for property in node.children().filter(Node::is_element) {
+            let value = property.text().unwrap_or_default().trim().to_string();
+            properties.insert(property.tag_name().name().to_string(), value);
+        }
+    }
+    properties.insert(String::from("project.artifactId"), artifact_id.clone());
+    properties.insert(String::from("project.version"), version.clone());
+    properties.insert(String::from("project.packaging"), packaging.clone());
+    let final_name = text(child(project, "build"), "finalName")
+        .unwrap_or_else(|| format!("{}-{}", artifact_id, version));
+    let final_name = interpolate(&final_name, &properties).ok_or_else(|| {
+        invalid(format!(
+            "Cannot resolve the properties in {} of {}",
+            final_name, POM
+        ))
+    })?;
+    let artifact = path
+        .join("target")
+        .join(format!("{}.{}", final_name, packaging));
+    if !artifact.is_file() {
+        warn!(
+            "Artifact {} declared in {} not found",
+            artifact.display(),
+            POM
+        );
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Artifact target/{}.{} declared in {} not found",
+                final_name, packaging, POM
+            ),
+        ));
+    }
+    Ok(artifact)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn text(node: Option<Node>, name: &str) -> Option<String> {
+    child(node?, name)?
+        .text()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(String::from)
+}
+
+fn interpolate(value: &str, properties: &HashMap<String, String>) -> Option<String> {
+    let mut value = value.to_string();
+    for _ in 0..MAX_INTERPOLATIONS {
+        let Some(start) = value.find("${") else {
+            return Some(value);
+        };
+        let end = start + value[start..].find('}')?;
+        let name = &value[start + 2..end];
+        let replacement = properties.get(name)?.clone();
+        value.replace_range(start..=end, &replacement);
+    }
+    (!value.contains("${")).then_some(value)
+}
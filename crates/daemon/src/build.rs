@@ -0,0 +1,136 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use crate::artifact;
+use crate::config::{BuildConfig, BuildTool};
+use crate::output::{Output, Stream};
+
+pub async fn build<P: AsRef<Path>>(
+    path: P,
+    config: &BuildConfig,
+    output: &Output,
+) -> io::Result<PathBuf> {
+    let path = path.as_ref();
+    let (program, args) = command(&config.tool);
+    info!(
+        "Building {} with `{} {}`",
+        path.display(),
+        program,
+        args.join(" ")
+    );
+    output.send(
+        Stream::Build,
+        format!("$ {} {}", program, args.join(" ")).trim_end(),
+    );
+    let mut child = match Command::new(program)
+        .current_dir(path)
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to run build: {}", e);
+            return Err(e);
+        }
+    };
+    let (stdout, stderr) = tokio::join!(
+        capture(child.stdout.take(), output),
+        capture(child.stderr.take(), output)
+    );
+    let status = child.wait().await?;
+    let msg = format!(
+        "build status: {}\nbuild stdout: \n{}build stderr: \n{}\n",
+        status, stdout, stderr
+    );
+    info!("{}", msg);
+    if !status.success() {
+        warn!("Failed to build backend");
+        return Err(io::Error::other(msg));
+    }
+    artifact::resolve(path, config)
+}
+
+fn command(tool: &BuildTool) -> (&'static str, Vec<String>) {
+    let mut args = Vec::new();
+    let program = match tool {
+        BuildTool::Maven {
+            wrapper,
+            goals,
+            profiles,
+            skip_tests,
+            args: extra,
+        } => {
+            args.extend(goals.iter().cloned());
+            if !profiles.is_empty() {
+                args.push(format!("-P{}", profiles.join(",")));
+            }
+            if *skip_tests {
+                args.push(String::from("-DskipTests"));
+            }
+            args.extend(extra.iter().cloned());
+            wrap(*wrapper, "mvn", "./mvnw", &mut args)
+        }
+        BuildTool::Gradle {
+            wrapper,
+            tasks,
+            skip_tests,
+            args: extra,
+        } => {
+            args.extend(tasks.iter().cloned());
+            if *skip_tests {
+                args.extend([String::from("-x"), String::from("test")]);
+            }
+            args.extend(extra.iter().cloned());
+            wrap(*wrapper, "gradle", "./gradlew", &mut args)
+        }
+        BuildTool::Shell { command } => {
+            args.extend([String::from("-c"), command.clone()]);
+            "sh"
+        }
+    };
+    (program, args)
+}
+
+fn wrap(
+    wrapper: bool,
+    program: &'static str,
+    script: &str,
+    args: &mut Vec<String>,
+) -> &'static str {
+    if wrapper {
+        args.insert(0, String::from(script));
+        "sh"
+    } else {
+        program
+    }
+}
+
+async fn capture<R: AsyncRead + Unpin>(reader: Option<R>, output: &Output) -> String {
+    let mut captured = String::new();
+    let Some(reader) = reader else {
+        return captured;
+    };
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                captured.push_str(&line);
+                captured.push('\n');
+                output.send(Stream::Build, line);
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read build output: {}", e);
+                break;
+            }
+        }
+    }
+    captured
+}
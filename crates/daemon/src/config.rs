@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::env::current_dir;
 use std::fs::{canonicalize, create_dir_all, read_to_string};
 use std::io;
@@ -8,7 +9,7 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub backend: BackendConfig,
+    pub backends: Vec<BackendConfig>,
     pub daemon: DaemonConfig,
 }
 
@@ -16,9 +17,17 @@ impl Config {
     pub fn new<P: AsRef<Path>>(path: P) -> io::Result<serde_json::Result<Self>> {
         match serde_json::from_str::<Self>(&read_to_string(path)?) {
             Ok(mut config) => {
-                let bwd = current_dir()?.join(config.backend.working_directory);
-                create_dir_all(&bwd)?;
-                config.backend.working_directory = canonicalize(bwd)?;
+                for backend in config.backends.iter_mut() {
+                    let bwd = current_dir()?.join(&backend.working_directory);
+                    create_dir_all(&bwd)?;
+                    backend.working_directory = canonicalize(bwd)?;
+                    if let Some(java_home) = &backend.java_home {
+                        backend.java_home = Some(current_dir()?.join(java_home));
+                    }
+                    if let Some(env_file) = &backend.env_file {
+                        backend.env_file = Some(current_dir()?.join(env_file));
+                    }
+                }
                 let log_dir = current_dir()?.join(config.daemon.log_directory);
                 create_dir_all(&log_dir)?;
                 config.daemon.log_directory = canonicalize(log_dir)?;
@@ -35,6 +44,169 @@ pub struct BackendConfig {
     pub working_directory: PathBuf,
     pub addr: IpAddr,
     pub port: u16,
+    #[serde(default = "BackendConfig::default_max_releases")]
+    pub max_releases: usize,
+    #[serde(default)]
+    pub health: HealthConfig,
+    #[serde(default)]
+    pub restart: RestartConfig,
+    #[serde(default = "BackendConfig::default_stop_timeout_secs")]
+    pub stop_timeout_secs: u64,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub build: BuildConfig,
+    #[serde(default)]
+    pub run: Option<String>,
+    #[serde(default)]
+    pub java_home: Option<PathBuf>,
+    #[serde(default)]
+    pub jvm_args: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub env_file: Option<PathBuf>,
+}
+
+impl BackendConfig {
+    fn default_max_releases() -> usize {
+        5
+    }
+
+    fn default_stop_timeout_secs() -> u64 {
+        30
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub path: Option<String>,
+    pub timeout_secs: u64,
+    pub interval_millis: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            timeout_secs: 60,
+            interval_millis: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "tool", rename_all = "kebab-case")]
+pub enum BuildTool {
+    Maven {
+        #[serde(default)]
+        wrapper: bool,
+        #[serde(default = "BuildTool::default_maven_goals")]
+        goals: Vec<String>,
+        #[serde(default)]
+        profiles: Vec<String>,
+        #[serde(default)]
+        skip_tests: bool,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    Gradle {
+        #[serde(default)]
+        wrapper: bool,
+        #[serde(default = "BuildTool::default_gradle_tasks")]
+        tasks: Vec<String>,
+        #[serde(default)]
+        skip_tests: bool,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    Shell {
+        command: String,
+    },
+}
+
+impl BuildTool {
+    fn default_maven_goals() -> Vec<String> {
+        vec![String::from("install")]
+    }
+
+    fn default_gradle_tasks() -> Vec<String> {
+        vec![String::from("build")]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildConfig {
+    #[serde(flatten)]
+    pub tool: BuildTool,
+    #[serde(default)]
+    pub artifact: Option<String>,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            tool: BuildTool::Maven {
+                wrapper: false,
+                goals: BuildTool::default_maven_goals(),
+                profiles: Vec::new(),
+                skip_tests: false,
+                args: Vec::new(),
+            },
+            artifact: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    pub buffer_lines: usize,
+    pub buffer_bytes: usize,
+    pub max_file_size: u64,
+    pub max_files: usize,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            buffer_lines: 10 * 1000,
+            buffer_bytes: 4 * 1024 * 1024,
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Never,
+    OnFailure,
+    Always,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestartConfig {
+    pub policy: RestartPolicy,
+    pub max_retries: u32,
+    pub initial_backoff_millis: u64,
+    pub max_backoff_millis: u64,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::Never,
+            max_retries: 5,
+            initial_backoff_millis: 1000,
+            max_backoff_millis: 60 * 1000,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +216,34 @@ pub struct DaemonConfig {
     pub time_format: String,
     pub log_directory: PathBuf,
     pub log_filename: String,
+    #[serde(default = "DaemonConfig::default_max_archive_size")]
+    pub max_archive_size: usize,
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    #[serde(default)]
+    pub trusted_keys: Vec<TrustedKey>,
+    #[serde(default)]
+    pub require_signature: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub certificate: PathBuf,
+    pub key: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedKey {
+    pub name: String,
+    pub public_key: String,
+}
+
+impl DaemonConfig {
+    fn default_max_archive_size() -> usize {
+        1024 * 1024 * 1024
+    }
 }
 
 impl From<DaemonConfig> for SocketAddr {
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout, Instant};
+use tracing::{info, warn};
+
+use crate::config::{BackendConfig, HealthConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Stopped,
+    Starting,
+    Healthy,
+    Unhealthy,
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Health::Stopped => "stopped",
+            Health::Starting => "starting",
+            Health::Healthy => "healthy",
+            Health::Unhealthy => "unhealthy",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthCheck {
+    addr: SocketAddr,
+    config: HealthConfig,
+}
+
+impl HealthCheck {
+    pub fn new(config: &BackendConfig) -> Self {
+        Self {
+            addr: SocketAddr::new(config.addr, config.port),
+            config: config.health.clone(),
+        }
+    }
+
+    pub async fn probe(&self) -> io::Result<()> {
+        let interval = Duration::from_millis(self.config.interval_millis);
+        let mut stream = timeout(interval, TcpStream::connect(self.addr)).await??;
+        let Some(path) = &self.config.path else {
+            return Ok(());
+        };
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            path, self.addr
+        );
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = Vec::new();
+        timeout(interval, stream.read_to_end(&mut response)).await??;
+        let response = String::from_utf8_lossy(&response);
+        let status = response
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok());
+        match status {
+            Some(code) if (200..300).contains(&code) => Ok(()),
+            Some(code) => Err(io::Error::other(format!(
+                "{} returned status {}",
+                path, code
+            ))),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} returned an invalid response", path),
+            )),
+        }
+    }
+
+    pub async fn wait(&self) -> io::Result<()> {
+        let deadline = Instant::now() + Duration::from_secs(self.config.timeout_secs);
+        info!("Waiting for backend to become healthy at {}", self.addr);
+        loop {
+            let e = match self.probe().await {
+                Ok(_) => {
+                    info!("Backend is healthy");
+                    return Ok(());
+                }
+                Err(e) => e,
+            };
+            if Instant::now() >= deadline {
+                warn!("Backend is unhealthy: {}", e);
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "Backend is not healthy after {}s: {}",
+                        self.config.timeout_secs, e
+                    ),
+                ));
+            }
+            sleep(Duration::from_millis(self.config.interval_millis)).await;
+        }
+    }
+}
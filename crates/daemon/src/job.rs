@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::Serialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::{error, info};
+
+use crate::api::response::{ApiResponse, ErrorKind};
+
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Update,
+    Deploy,
+    Start,
+    Restart,
+    Rollback,
+}
+
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            JobKind::Update => "update",
+            JobKind::Deploy => "deploy",
+            JobKind::Start => "start",
+            JobKind::Restart => "restart",
+            JobKind::Rollback => "rollback",
+        };
+        f.write_str(kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+        };
+        f.write_str(status)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub kind: JobKind,
+    pub backend: String,
+    pub status: JobStatus,
+    pub steps: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub finished: Option<OffsetDateTime>,
+    pub result: Option<Value>,
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Job {} ({} {}): {}",
+            self.id, self.kind, self.backend, self.status
+        )?;
+        for step in &self.steps {
+            write!(f, "\n  {}", step)?;
+        }
+        if let Some(message) = self
+            .result
+            .as_ref()
+            .and_then(|result| result.get("message"))
+            .and_then(Value::as_str)
+        {
+            write!(f, "\n{}", message)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+#[derive(Clone, Default)]
+pub struct Jobs(Arc<Mutex<Registry>>);
+
+impl Jobs {
+    fn lock(&self) -> MutexGuard<'_, Registry> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                error!("Failed to lock Jobs: {}", poisoned);
+                poisoned.into_inner()
+            }
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.lock().jobs.get(&id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.lock().jobs.values().rev().cloned().collect()
+    }
+
+    pub fn spawn<F, Fut>(&self, kind: JobKind, backend: String, task: F) -> io::Result<Job>
+    where
+        F: FnOnce(JobHandle) -> Fut,
+        Fut: Future<Output = ApiResponse> + Send + 'static,
+    {
+        let job = {
+            let mut registry = self.lock();
+            if let Some(running) = registry
+                .jobs
+                .values()
+                .find(|job| job.backend == backend && job.status == JobStatus::Running)
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::ResourceBusy,
+                    format!(
+                        "Backend {} is busy with job {} ({})",
+                        backend, running.id, running.kind
+                    ),
+                ));
+            }
+            registry.next_id += 1;
+            let job = Job {
+                id: registry.next_id,
+                kind,
+                backend,
+                status: JobStatus::Running,
+                steps: Vec::new(),
+                created: OffsetDateTime::now_utc(),
+                finished: None,
+                result: None,
+            };
+            registry.jobs.insert(job.id, job.clone());
+            registry.prune();
+            job
+        };
+        info!("Started job {} ({} {})", job.id, job.kind, job.backend);
+        let handle = JobHandle {
+            id: job.id,
+            jobs: self.clone(),
+        };
+        let task = tokio::spawn(task(handle.clone()));
+        tokio::spawn(async move {
+            let response = task.await.unwrap_or_else(|e| {
+                ApiResponse::error(ErrorKind::Internal, format!("Job panicked: {}", e))
+            });
+            handle.finish(response);
+        });
+        Ok(job)
+    }
+}
+
+impl Registry {
+    fn prune(&mut self) {
+        let finished = self
+            .jobs
+            .values()
+            .filter(|job| job.status != JobStatus::Running)
+            .map(|job| job.id)
+            .collect::<Vec<_>>();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+        {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JobHandle {
+    id: u64,
+    jobs: Jobs,
+}
+
+impl JobHandle {
+    pub fn step<S: Into<String>>(&self, step: S) {
+        let step = step.into();
+        info!("Job {}: {}", self.id, step);
+        if let Some(job) = self.jobs.lock().jobs.get_mut(&self.id) {
+            job.steps.push(step);
+        }
+    }
+
+    fn finish(&self, response: ApiResponse) {
+        let status = if response.is_ok() {
+            JobStatus::Succeeded
+        } else {
+            JobStatus::Failed
+        };
+        info!("Job {} {}", self.id, status);
+        if let Some(job) = self.jobs.lock().jobs.get_mut(&self.id) {
+            job.status = status;
+            job.finished = Some(OffsetDateTime::now_utc());
+            job.result = Some(response.to_value());
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::read_to_string;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::BackendConfig;
+
+const SECRET_MARKERS: [&str; 6] = ["PASSWORD", "PASSWD", "SECRET", "TOKEN", "KEY", "CREDENTIAL"];
+const REDACTED: &str = "******";
+
+#[derive(Debug, Clone)]
+pub struct Launch {
+    run: Option<String>,
+    java_home: Option<PathBuf>,
+    jvm_args: Vec<String>,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    env_file: Option<PathBuf>,
+}
+
+pub struct Prepared {
+    pub command: Command,
+    pub description: String,
+    pub secrets: Vec<String>,
+}
+
+impl Launch {
+    pub fn new(config: &BackendConfig) -> Self {
+        Self {
+            run: config.run.clone(),
+            java_home: config.java_home.clone(),
+            jvm_args: config.jvm_args.clone(),
+            args: config.args.clone(),
+            env: config.env.clone(),
+            env_file: config.env_file.clone(),
+        }
+    }
+
+    pub fn prepare(&self, artifact: &Path) -> io::Result<Prepared> {
+        let mut secrets = Vec::new();
+        let mut env = BTreeMap::new();
+        if let Some(env_file) = &self.env_file {
+            for (name, value) in parse_env_file(env_file)? {
+                if is_secret(&name) {
+                    secrets.push(value.clone());
+                }
+                env.insert(name, value);
+            }
+        }
+        for (name, value) in &self.env {
+            if is_secret(name) {
+                secrets.push(value.clone());
+            }
+            env.insert(name.clone(), value.clone());
+        }
+        if let Some(java_home) = &self.java_home {
+            env.insert(
+                String::from("JAVA_HOME"),
+                java_home.to_string_lossy().into_owned(),
+            );
+        }
+        let (program, args): (OsString, Vec<OsString>) = match &self.run {
+            Some(run) => {
+                env.insert(
+                    String::from("ARTIFACT"),
+                    artifact.to_string_lossy().into_owned(),
+                );
+                ("sh".into(), vec!["-c".into(), run.into()])
+            }
+            None => {
+                let java = match &self.java_home {
+                    Some(java_home) => java_home.join("bin").join("java").into_os_string(),
+                    None => "java".into(),
+                };
+                let mut args = self.jvm_args.iter().map(OsString::from).collect::<Vec<_>>();
+                args.push("-jar".into());
+                args.push(artifact.into());
+                args.extend(self.args.iter().map(OsString::from));
+                (java, args)
+            }
+        };
+        for arg in self.jvm_args.iter().chain(&self.args) {
+            if let Some((name, value)) = arg.split_once('=') {
+                if is_secret(name) {
+                    secrets.push(value.to_string());
+                }
+            }
+        }
+        secrets.retain(|secret| !secret.is_empty());
+        let mut command = Command::new(&program);
+        command.args(&args).envs(&env).process_group(0);
+        let description = redact(
+            &std::iter::once(&program)
+                .chain(&args)
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" "),
+            &secrets,
+        );
+        Ok(Prepared {
+            command,
+            description,
+            secrets,
+        })
+    }
+}
+
+pub fn redact(text: &str, secrets: &[String]) -> String {
+    let mut text = text.to_string();
+    for secret in secrets {
+        if text.contains(secret.as_str()) {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+    }
+    text
+}
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+fn parse_env_file(path: &Path) -> io::Result<Vec<(String, String)>> {
+    let content = read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to read env file {}: {}", path.display(), e),
+        )
+    })?;
+    let mut vars = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid line {} in env file {}, expected NAME=VALUE",
+                    index + 1,
+                    path.display()
+                ),
+            ));
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| {
+                value
+                    .strip_prefix('\'')
+                    .and_then(|value| value.strip_suffix('\''))
+            })
+            .unwrap_or(value);
+        vars.push((name.trim().to_string(), value.to_string()));
+    }
+    Ok(vars)
+}
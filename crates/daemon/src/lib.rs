@@ -1,36 +1,131 @@
-use std::borrow::Cow;
-use std::ffi::OsStr;
-use std::fs::canonicalize;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::io;
-use std::path::PathBuf;
-use std::process::{Child, Command, Output};
-use std::process::{ChildStderr, ChildStdout, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::process::{Child, ExitStatus};
 use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
-use nonblock::NonBlockingReader;
+use nix::errno::Errno;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::task;
+use tokio::time::sleep;
 use tracing::{error, info, warn};
 
+use crate::artifact::Artifact;
+use crate::config::{BackendConfig, BuildConfig, DaemonConfig, RestartConfig};
+use crate::health::{Health, HealthCheck};
+use crate::job::Jobs;
+use crate::launch::{Launch, Prepared};
+use crate::output::{Filter, Output, Stream};
+use crate::release::{Release, Releases};
+use crate::signature::Signers;
+use crate::supervisor::Exit;
+use crate::upload::Uploads;
+
 pub mod api;
+pub mod archive;
+pub mod artifact;
+pub mod build;
 pub mod config;
+pub mod health;
+pub mod job;
+pub mod launch;
+pub mod output;
+pub mod release;
+pub mod signature;
+pub mod supervisor;
+pub mod upload;
+
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const STATUS_TAIL_LINES: usize = 100;
 
 #[derive(Clone)]
-pub struct AppState(Arc<Mutex<Backend>>);
+pub struct AppState {
+    backends: Arc<BTreeMap<String, BackendState>>,
+    jobs: Jobs,
+    signers: Signers,
+    max_archive_size: usize,
+}
 
 impl AppState {
-    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
-        Self(Arc::new(Mutex::new(Backend::new(path))))
+    pub fn new(configs: Vec<BackendConfig>, daemon: &DaemonConfig) -> io::Result<Self> {
+        let signers = Signers::new(&daemon.trusted_keys, daemon.require_signature)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut backends = BTreeMap::new();
+        for config in configs {
+            let name = config.name.clone();
+            if backends
+                .insert(
+                    name.clone(),
+                    BackendState::new(config, &daemon.log_directory)?,
+                )
+                .is_some()
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Duplicate backend name: {}", name),
+                ));
+            }
+        }
+        Ok(Self {
+            backends: Arc::new(backends),
+            jobs: Jobs::default(),
+            signers,
+            max_archive_size: daemon.max_archive_size,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<BackendState> {
+        self.backends.get(name).cloned()
+    }
+
+    pub fn backends(&self) -> impl Iterator<Item = &BackendState> {
+        self.backends.values()
+    }
+
+    pub fn jobs(&self) -> Jobs {
+        self.jobs.clone()
+    }
+
+    pub fn signers(&self) -> Signers {
+        self.signers.clone()
+    }
+
+    pub fn max_archive_size(&self) -> usize {
+        self.max_archive_size
+    }
+}
+
+#[derive(Clone)]
+pub struct BackendState(Arc<Mutex<Backend>>);
+
+impl BackendState {
+    pub fn new<P: AsRef<Path>>(config: BackendConfig, log_directory: P) -> io::Result<Self> {
+        Ok(Self(Arc::new(Mutex::new(Backend::new(
+            config,
+            log_directory,
+        )?))))
     }
 
-    pub fn lock(&self) -> MutexGuard<Backend> {
+    pub fn lock(&self) -> MutexGuard<'_, Backend> {
         match self.0.lock() {
             Ok(guard) => guard,
             Err(poisoned) => {
-                error!("Failed to lock AppState: {}", poisoned);
+                error!("Failed to lock BackendState: {}", poisoned);
                 poisoned.into_inner()
             }
         }
     }
-    
+
+    pub fn name(&self) -> String {
+        self.lock().name()
+    }
+
     pub fn running(&self) -> bool {
         self.lock().running()
     }
@@ -39,211 +134,433 @@ impl AppState {
         self.lock().path()
     }
 
-    pub fn commit_info(&self) -> io::Result<(String, String)> {
-        self.lock().commit_info()
+    pub fn releases(&self) -> Releases {
+        self.lock().releases()
     }
 
-    pub fn stdout(&self) -> io::Result<Cow<'static, str>> {
+    pub fn uploads(&self) -> Uploads {
+        self.lock().uploads()
+    }
+
+    pub fn stdout(&self) -> Option<String> {
         self.lock().stdout()
     }
 
-    pub fn stderr(&self) -> io::Result<Cow<'static, str>> {
+    pub fn stderr(&self) -> Option<String> {
         self.lock().stderr()
     }
 
-    pub fn start(&self) -> io::Result<()> {
-        self.lock().start()
+    pub fn output(&self) -> Output {
+        self.lock().output()
+    }
+
+    pub fn build_config(&self) -> BuildConfig {
+        self.lock().build_config()
+    }
+
+    pub fn health(&self) -> Health {
+        self.lock().health()
+    }
+
+    pub async fn check_health(&self) -> Health {
+        let check = {
+            let backend = self.lock();
+            match backend.health() {
+                Health::Stopped | Health::Starting => return backend.health(),
+                _ => backend.health_check(),
+            }
+        };
+        let health = match check.probe().await {
+            Ok(_) => Health::Healthy,
+            Err(e) => {
+                warn!("Backend health check failed: {}", e);
+                Health::Unhealthy
+            }
+        };
+        let mut backend = self.lock();
+        if backend.running() {
+            backend.health = health;
+        }
+        backend.health()
+    }
+
+    pub async fn wait_healthy(&self) -> io::Result<()> {
+        let check = self.lock().health_check();
+        match check.wait().await {
+            Ok(_) => {
+                self.lock().health = Health::Healthy;
+                Ok(())
+            }
+            Err(e) => {
+                self.lock().health = Health::Unhealthy;
+                warn!("Stopping unhealthy backend");
+                if let Err(e) = self.terminate().await {
+                    warn!("Failed to stop unhealthy backend: {}", e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn start(&self) -> io::Result<bool> {
+        if self.running() {
+            warn!("Backend is already running");
+            return Ok(false);
+        }
+        let (path, build) = {
+            let backend = self.lock();
+            (backend.path(), backend.build_config())
+        };
+        let artifact = task::spawn_blocking(move || {
+            artifact::resolve(path, &build).and_then(Artifact::inspect)
+        })
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)))?;
+        if !self.lock().start(artifact)? {
+            return Ok(false);
+        }
+        self.wait_healthy().await?;
+        Ok(true)
+    }
+
+    pub async fn stop(&self) -> io::Result<Option<Stopped>> {
+        self.lock().should_run = false;
+        self.terminate().await
     }
 
-    pub fn stop(&self) -> io::Result<Cow<'static, str>> {
-        self.lock().stop()
+    async fn terminate(&self) -> io::Result<Option<Stopped>> {
+        let (process, timeout) = {
+            let mut backend = self.lock();
+            (backend.take_process(), backend.stop_timeout)
+        };
+        match process {
+            Some(process) => {
+                let stopped = process.terminate(timeout).await?;
+                info!("{}", stopped);
+                Ok(Some(stopped))
+            }
+            None => {
+                warn!("Backend is not running");
+                Ok(None)
+            }
+        }
     }
 
-    pub fn restart(&self) -> io::Result<()> {
-        self.lock().restart()
+    pub async fn restart(&self) -> io::Result<()> {
+        self.stop().await?;
+        self.start().await.map(|_| ())
+    }
+
+    pub async fn rollback(&self, id: &str) -> io::Result<Release> {
+        let releases = self.releases();
+        let release = releases
+            .list()?
+            .into_iter()
+            .find(|release| release.id == id)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("Release {} not found", id))
+            })?;
+        let was_running = self.running();
+        self.stop().await?;
+        releases.activate(&release.id)?;
+        info!("Rolled back to release {}", release.id);
+        if was_running {
+            self.start().await?;
+        }
+        Ok(Release {
+            active: true,
+            ..release
+        })
     }
 }
 
 pub struct Backend {
+    name: String,
     process: Option<BackendProcess>,
     path: PathBuf,
+    releases: Releases,
+    uploads: Uploads,
+    health: Health,
+    health_check: HealthCheck,
+    restart: RestartConfig,
+    should_run: bool,
+    stop_timeout: Duration,
+    exit: Option<Exit>,
+    last_exit: Option<Exit>,
+    output: Output,
+    build: BuildConfig,
+    launch: Launch,
 }
 
 impl Backend {
-    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
-        Self {
+    pub fn new<P: AsRef<Path>>(config: BackendConfig, log_directory: P) -> io::Result<Self> {
+        let output = Output::new(
+            log_directory.as_ref().join(format!("{}.log", config.name)),
+            &config.output,
+        )?;
+        let releases = Releases::new(&config.working_directory, config.max_releases)?;
+        Ok(Self {
+            name: config.name.clone(),
             process: None,
-            path: path.into(),
-        }
+            path: releases.current_path(),
+            releases,
+            uploads: Uploads::new(&config.working_directory)?,
+            health: Health::Stopped,
+            health_check: HealthCheck::new(&config),
+            restart: config.restart.clone(),
+            should_run: false,
+            stop_timeout: Duration::from_secs(config.stop_timeout_secs),
+            exit: None,
+            last_exit: None,
+            output,
+            launch: Launch::new(&config),
+            build: config.build,
+        })
     }
 
-    pub fn running(&self) -> bool {
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn running(&mut self) -> bool {
+        self.poll_exit();
         self.process.is_some()
     }
 
-    pub fn path(&self) -> PathBuf {
-        self.path.clone()
+    pub fn restart_config(&self) -> RestartConfig {
+        self.restart.clone()
     }
 
-    pub fn commit_info(&self) -> io::Result<(String, String)> {
-        let output = Command::new("git")
-            .current_dir(&self.path)
-            .args(["log", "-n", "1"])
-            .output()?;
-        Ok((
-            String::from_utf8_lossy(&output.stdout).into_owned(),
-            String::from_utf8_lossy(&output.stderr).into_owned(),
-        ))
+    pub fn should_run(&self) -> bool {
+        self.should_run
     }
 
-    pub fn stdout(&mut self) -> io::Result<Cow<'static, str>> {
-        match self.process.as_mut() {
-            Some(process) => process.stdout().map(Cow::Owned),
-            None => {
-                warn!("Backend is not running");
-                Ok(Cow::Borrowed("Backend is not running"))
-            }
-        }
+    pub fn last_exit(&self) -> Option<Exit> {
+        self.last_exit.clone()
     }
 
-    pub fn stderr(&mut self) -> io::Result<Cow<'static, str>> {
-        match self.process.as_mut() {
-            Some(process) => process.stderr().map(Cow::Owned),
-            None => {
-                warn!("Backend is not running");
-                Ok(Cow::Borrowed("Backend is not running"))
+    pub fn take_exit(&mut self) -> Option<Exit> {
+        self.poll_exit();
+        self.exit.take()
+    }
+
+    fn poll_exit(&mut self) {
+        let Some(process) = self.process.as_mut() else {
+            return;
+        };
+        match process.try_wait() {
+            Ok(Some(status)) => {
+                let exit = Exit {
+                    status,
+                    time: OffsetDateTime::now_utc(),
+                    uptime: process.uptime(),
+                };
+                self.process = None;
+                self.health = Health::Stopped;
+                self.exit = Some(exit.clone());
+                self.last_exit = Some(exit);
             }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to poll backend process: {}", e),
         }
     }
 
-    pub fn start(&mut self) -> io::Result<()> {
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    pub fn releases(&self) -> Releases {
+        self.releases.clone()
+    }
+
+    pub fn uploads(&self) -> Uploads {
+        self.uploads.clone()
+    }
+
+    pub fn health(&self) -> Health {
+        self.health
+    }
+
+    pub fn health_check(&self) -> HealthCheck {
+        self.health_check.clone()
+    }
+
+    pub fn stdout(&self) -> Option<String> {
+        self.process.as_ref().map(BackendProcess::stdout)
+    }
+
+    pub fn stderr(&self) -> Option<String> {
+        self.process.as_ref().map(BackendProcess::stderr)
+    }
+
+    pub fn output(&self) -> Output {
+        self.output.clone()
+    }
+
+    pub fn build_config(&self) -> BuildConfig {
+        self.build.clone()
+    }
+
+    pub fn artifact(&self) -> Option<Artifact> {
+        self.process
+            .as_ref()
+            .map(|process| process.artifact.clone())
+    }
+
+    pub fn start(&mut self, artifact: Artifact) -> io::Result<bool> {
+        self.should_run = true;
         if self.running() {
             warn!("Backend is already running");
-            return Ok(());
-        }
-        info!("Installing dependencies");
-        match Command::new("mvn")
-            .current_dir(&self.path)
-            .arg("install")
-            .output()
-        {
-            Ok(output) => {
-                let msg = format!(
-                    "maven install status: {}\nmaven install stdout: \n{}maven install stderr: \n{}\n",
-                    output.status,
-                    String::from_utf8_lossy(&output.stdout),
-                    String::from_utf8_lossy(&output.stderr)
-                );
-                info!("{}", msg);
-                if !output.status.success() {
-                    warn!("Failed to install dependencies");
-                    return Err(io::Error::new(io::ErrorKind::Other, msg));
-                }
-            }
-            Err(e) => {
-                warn!("Failed to install dependencies: {}", e);
-                return Err(e);
-            }
+            return Ok(false);
         }
-        for entry in self.path.join("target").read_dir()?.filter_map(Result::ok) {
-            if entry.file_name().to_string_lossy().ends_with(".jar") {
-                let jar = canonicalize(entry.path())?;
-                info!("Found jar: {}", entry.path().display());
-                self.process = Some(BackendProcess::new(jar)?);
-                return Ok(());
-            }
-        }
-        warn!("No jar found in target directory");
-        Err(io::Error::new(io::ErrorKind::NotFound, "No jar found"))
+        let prepared = self.launch.prepare(&artifact.path)?;
+        self.process = Some(BackendProcess::new(prepared, artifact, &self.output)?);
+        self.health = Health::Starting;
+        Ok(true)
     }
 
-    pub fn stop(&mut self) -> io::Result<Cow<'static, str>> {
-        match self.process.take() {
-            Some(process) => {
-                let output = process.kill()?;
-                let msg = format!(
-                    "Backend stopped with status: {}\nstdout: \n{}\nstderr:\n {}\n",
-                    output.status,
-                    String::from_utf8_lossy(&output.stdout),
-                    String::from_utf8_lossy(&output.stderr)
-                );
-                info!("{}", msg);
-                Ok(Cow::Owned(msg))
-            }
-            None => {
-                warn!("Backend is not running");
-                Ok(Cow::Borrowed("Backend is not running"))
+    pub fn take_process(&mut self) -> Option<BackendProcess> {
+        self.health = Health::Stopped;
+        self.process.take()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shutdown {
+    Graceful,
+    Killed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Stopped {
+    pub shutdown: Shutdown,
+    pub status: String,
+    pub code: Option<i32>,
+    pub timeout_secs: u64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Stopped {
+    pub fn summary(&self) -> String {
+        match self.shutdown {
+            Shutdown::Graceful => {
+                format!("Backend stopped gracefully with status: {}", self.status)
             }
+            Shutdown::Killed => format!(
+                "Backend did not stop within {}s after SIGTERM, killed with status: {}",
+                self.timeout_secs, self.status
+            ),
         }
     }
+}
 
-    pub fn restart(&mut self) -> io::Result<()> {
-        self.stop()?;
-        self.start()
+impl fmt::Display for Stopped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\nstdout: \n{}\nstderr:\n {}",
+            self.summary(),
+            self.stdout,
+            self.stderr
+        )
     }
 }
 
 pub struct BackendProcess {
     process: Child,
-    stdout: String,
-    stdout_rd: NonBlockingReader<ChildStdout>,
-    stderr: String,
-    stderr_rd: NonBlockingReader<ChildStderr>,
+    started: Instant,
+    started_at: OffsetDateTime,
+    artifact: Artifact,
+    output: Output,
 }
 
 impl BackendProcess {
-    pub fn new<S: AsRef<OsStr>>(jar: S) -> io::Result<Self> {
-        let mut process = Command::new("java")
-            .arg("-jar")
-            .arg(jar)
+    pub fn new(prepared: Prepared, artifact: Artifact, output: &Output) -> io::Result<Self> {
+        let Prepared {
+            mut command,
+            description,
+            secrets,
+        } = prepared;
+        info!("Starting backend: {}", description);
+        output.redact(secrets);
+        let mut process = command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
+        output.capture(process.stdout.take().unwrap(), Stream::Stdout);
+        output.capture(process.stderr.take().unwrap(), Stream::Stderr);
         Ok(Self {
-            stdout_rd: NonBlockingReader::from_fd(process.stdout.take().unwrap())?,
-            stdout: String::new(),
-            stderr_rd: NonBlockingReader::from_fd(process.stderr.take().unwrap())?,
-            stderr: String::new(),
             process,
+            started: Instant::now(),
+            started_at: OffsetDateTime::now_utc(),
+            artifact,
+            output: output.clone(),
         })
     }
 
-    pub fn kill(self) -> io::Result<Output> {
-        Command::new("kill")
-            .arg(self.process.id().to_string())
-            .output()?;
-        self.process.wait_with_output()
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.process.try_wait()
     }
 
-    pub fn poll_stdout(&mut self) -> io::Result<Option<String>> {
-        let mut output = String::new();
-        if self.stdout_rd.read_available_to_string(&mut output)? == 0 {
-            Ok(None)
-        } else {
-            Ok(Some(output))
-        }
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
     }
 
-    pub fn stdout(&mut self) -> io::Result<String> {
-        while let Some(output) = self.poll_stdout()? {
-            self.stdout.push_str(&output);
-        }
-        Ok(self.stdout.clone())
+    pub async fn terminate(mut self, timeout: Duration) -> io::Result<Stopped> {
+        let pid = Pid::from_raw(self.process.id() as i32);
+        info!("Sending SIGTERM to backend process group {}", pid);
+        signal::killpg(pid, Signal::SIGTERM).map_err(io::Error::from)?;
+        let deadline = Instant::now() + timeout;
+        let mut shutdown = Shutdown::Graceful;
+        let mut exited = None;
+        let status = loop {
+            if exited.is_none() {
+                exited = self.process.try_wait()?;
+            }
+            if let Some(status) = exited {
+                if signal::killpg(pid, None).is_err() {
+                    break status;
+                }
+            }
+            if shutdown == Shutdown::Graceful && Instant::now() >= deadline {
+                warn!(
+                    "Backend process group {} did not exit within {}s, sending SIGKILL",
+                    pid,
+                    timeout.as_secs()
+                );
+                match signal::killpg(pid, Signal::SIGKILL) {
+                    Ok(_) | Err(Errno::ESRCH) => {}
+                    Err(e) => return Err(e.into()),
+                }
+                shutdown = Shutdown::Killed;
+            }
+            sleep(STOP_POLL_INTERVAL).await;
+        };
+        Ok(Stopped {
+            shutdown,
+            status: status.to_string(),
+            code: status.code(),
+            timeout_secs: timeout.as_secs(),
+            stdout: self.stdout(),
+            stderr: self.stderr(),
+        })
     }
 
-    pub fn poll_stderr(&mut self) -> io::Result<Option<String>> {
-        let mut output = String::new();
-        if self.stderr_rd.read_available_to_string(&mut output)? == 0 {
-            Ok(None)
-        } else {
-            Ok(Some(output))
-        }
+    pub fn stdout(&self) -> String {
+        self.tail(Stream::Stdout)
     }
 
-    pub fn stderr(&mut self) -> io::Result<String> {
-        while let Some(output) = self.poll_stderr()? {
-            self.stderr.push_str(&output);
-        }
-        Ok(self.stderr.clone())
+    pub fn stderr(&self) -> String {
+        self.tail(Stream::Stderr)
+    }
+
+    fn tail(&self, stream: Stream) -> String {
+        self.output.text(&Filter {
+            streams: vec![stream],
+            since: Some(self.started_at),
+            tail: Some(STATUS_TAIL_LINES),
+        })
     }
 }
@@ -3,16 +3,18 @@ use std::fs::File;
 use std::io;
 use std::net::SocketAddr;
 
-use axum::Router;
+use axum::{middleware, Router};
+use axum_server::tls_rustls::RustlsConfig;
 use time::format_description::parse_owned;
 use time::UtcOffset;
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::fmt::{self, time::OffsetTime};
 use tracing_subscriber::layer::SubscriberExt;
 
+use daemon::api::auth::Tokens;
 use daemon::config::Config;
-use daemon::{api, AppState};
+use daemon::{api, supervisor, AppState};
 
 const CONFIG_PATH: &str = "config/daemon/config.json";
 
@@ -26,13 +28,39 @@ async fn main() -> io::Result<()> {
     config_tracing(&config)?;
 
     let socket_addr = SocketAddr::from((config.daemon.addr, config.daemon.port));
-    let listener = TcpListener::bind(socket_addr).await?;
-    info!("Listening on {}", socket_addr);
+    let tokens = match Tokens::new(&config.daemon.tokens) {
+        Ok(tokens) => tokens,
+        Err(e) => panic!("Invalid config: {}", e),
+    };
+    if tokens.is_empty() {
+        warn!("No tokens configured, the API is accessible without authentication");
+    }
+    let state = AppState::new(config.backends, &config.daemon)?;
+    for backend in state.backends() {
+        tokio::spawn(supervisor::supervise(backend.clone()));
+    }
     let app = Router::new()
         .nest(api::backend::PATH, api::backend::routes())
-        .with_state(AppState::new(config.backend.working_directory))
-        .layer(DefaultBodyLimit::disable());
-    axum::serve(listener, app).await
+        .nest(api::job::PATH, api::job::routes())
+        .route_layer(middleware::from_fn_with_state(tokens, api::auth::authorize))
+        .with_state(state)
+        .layer(middleware::from_fn(api::response::negotiate))
+        .layer(DefaultBodyLimit::max(config.daemon.max_archive_size));
+    match &config.daemon.tls {
+        Some(tls) => {
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let rustls_config = RustlsConfig::from_pem_file(&tls.certificate, &tls.key).await?;
+            info!("Listening on {} with TLS", socket_addr);
+            axum_server::bind_rustls(socket_addr, rustls_config)
+                .serve(app.into_make_service())
+                .await
+        }
+        None => {
+            let listener = TcpListener::bind(socket_addr).await?;
+            info!("Listening on {}", socket_addr);
+            axum::serve(listener, app).await
+        }
+    }
 }
 
 fn config_tracing(config: &Config) -> io::Result<()> {
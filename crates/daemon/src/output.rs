@@ -0,0 +1,265 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tracing::{error, info, warn};
+
+use crate::config::OutputConfig;
+use crate::launch;
+
+const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+    Build,
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stream = match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+            Stream::Build => "build",
+        };
+        f.write_str(stream)
+    }
+}
+
+impl FromStr for Stream {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(Stream::Stdout),
+            "stderr" => Ok(Stream::Stderr),
+            "build" => Ok(Stream::Build),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown stream: {}", other),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Line {
+    pub stream: Stream,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+    pub text: String,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self.time.format(&Rfc3339).unwrap_or_default();
+        write!(f, "{} [{}] {}", time, self.stream, self.text)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub streams: Vec<Stream>,
+    pub since: Option<OffsetDateTime>,
+    pub tail: Option<usize>,
+}
+
+impl Filter {
+    pub fn matches(&self, line: &Line) -> bool {
+        (self.streams.is_empty() || self.streams.contains(&line.stream))
+            && self.since.is_none_or(|since| line.time >= since)
+    }
+}
+
+struct Buffer {
+    lines: VecDeque<Line>,
+    bytes: usize,
+    max_lines: usize,
+    max_bytes: usize,
+}
+
+impl Buffer {
+    fn push(&mut self, line: Line) {
+        self.bytes += line.text.len();
+        self.lines.push_back(line);
+        while self.lines.len() > self.max_lines || self.bytes > self.max_bytes {
+            match self.lines.pop_front() {
+                Some(line) => self.bytes -= line.text.len(),
+                None => break,
+            }
+        }
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl LogFile {
+    fn open(path: PathBuf, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        info!("Writing backend output to {}", path.display());
+        Ok(Self {
+            path,
+            file: Some(file),
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn write(&mut self, line: &Line) -> io::Result<()> {
+        let entry = format!("{}\n", line);
+        if self.size > 0 && self.size + entry.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        file.write_all(entry.as_bytes())?;
+        self.size += entry.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        for index in (1..self.max_files).rev() {
+            let from = rotated(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated(&self.path, index + 1))?;
+            }
+        }
+        if self.max_files > 0 {
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+        self.file = Some(File::create(&self.path)?);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+#[derive(Clone)]
+pub struct Output {
+    sender: Sender<Line>,
+    buffer: Arc<Mutex<Buffer>>,
+    file: Arc<Mutex<LogFile>>,
+    secrets: Arc<Mutex<Vec<String>>>,
+}
+
+impl Output {
+    pub fn new<P: AsRef<Path>>(path: P, config: &OutputConfig) -> io::Result<Self> {
+        Ok(Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            buffer: Arc::new(Mutex::new(Buffer {
+                lines: VecDeque::new(),
+                bytes: 0,
+                max_lines: config.buffer_lines,
+                max_bytes: config.buffer_bytes,
+            })),
+            file: Arc::new(Mutex::new(LogFile::open(
+                path.as_ref().to_path_buf(),
+                config.max_file_size,
+                config.max_files,
+            )?)),
+            secrets: Arc::default(),
+        })
+    }
+
+    pub fn subscribe(&self) -> Receiver<Line> {
+        self.sender.subscribe()
+    }
+
+    pub fn lines(&self, filter: &Filter) -> Vec<Line> {
+        let buffer = lock(&self.buffer);
+        let mut lines = buffer
+            .lines
+            .iter()
+            .rev()
+            .filter(|line| filter.matches(line))
+            .take(filter.tail.unwrap_or(usize::MAX))
+            .cloned()
+            .collect::<Vec<_>>();
+        lines.reverse();
+        lines
+    }
+
+    pub fn text(&self, filter: &Filter) -> String {
+        self.lines(filter)
+            .into_iter()
+            .map(|line| line.text + "\n")
+            .collect()
+    }
+
+    pub fn redact(&self, secrets: Vec<String>) {
+        *lock(&self.secrets) = secrets;
+    }
+
+    pub fn send<S: Into<String>>(&self, stream: Stream, text: S) {
+        let line = Line {
+            stream,
+            time: OffsetDateTime::now_utc(),
+            text: launch::redact(&text.into(), &lock(&self.secrets)),
+        };
+        if let Err(e) = lock(&self.file).write(&line) {
+            warn!("Failed to write backend output to file: {}", e);
+        }
+        lock(&self.buffer).push(line.clone());
+        let _ = self.sender.send(line);
+    }
+
+    pub fn capture<R: Read + Send + 'static>(&self, reader: R, stream: Stream) -> JoinHandle<()> {
+        let output = self.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut bytes = Vec::new();
+            loop {
+                bytes.clear();
+                match reader.read_until(b'\n', &mut bytes) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        let line = String::from_utf8_lossy(&bytes);
+                        output.send(stream, line.trim_end_matches(['\r', '\n']));
+                    }
+                    Err(e) => {
+                        warn!("Failed to read backend {}: {}", stream, e);
+                        break;
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            error!("Failed to lock Output: {}", poisoned);
+            poisoned.into_inner()
+        }
+    }
+}
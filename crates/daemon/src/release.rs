@@ -0,0 +1,230 @@
+use std::fmt;
+use std::fs::{self, create_dir_all, read_link, read_to_string, remove_dir_all, rename};
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+const RELEASES_DIR: &str = "releases";
+const CURRENT_LINK: &str = "current";
+const METADATA_FILE: &str = ".release.json";
+const SHORT_COMMIT_LEN: usize = 7;
+
+#[derive(Debug, Clone)]
+pub struct Releases {
+    root: PathBuf,
+    keep: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Release {
+    pub id: String,
+    pub path: PathBuf,
+    pub active: bool,
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    pub commit: Option<String>,
+    pub branch: Option<String>,
+    pub author: Option<String>,
+    pub message: Option<String>,
+    pub dirty: Option<bool>,
+    pub detached: Option<bool>,
+    pub upstream: Option<String>,
+    pub unpushed: Option<u32>,
+    pub allow_dirty: Option<bool>,
+    pub archive_sha256: Option<String>,
+    pub signer: Option<String>,
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.commit, &self.branch) {
+            (Some(commit), Some(branch)) => write!(f, "{} on {}", commit, branch)?,
+            (Some(commit), None) => write!(f, "{}", commit)?,
+            (None, _) => write!(f, "unknown")?,
+        }
+        let mut flags = Vec::new();
+        if self.dirty == Some(true) {
+            flags.push(String::from("dirty"));
+        }
+        if self.detached == Some(true) {
+            flags.push(String::from("detached"));
+        }
+        if let Some(unpushed) = self.unpushed.filter(|unpushed| *unpushed > 0) {
+            flags.push(format!("{} unpushed", unpushed));
+        }
+        if self.allow_dirty == Some(true) {
+            flags.push(String::from("allow-dirty"));
+        }
+        if !flags.is_empty() {
+            write!(f, " ({})", flags.join(", "))?;
+        }
+        if let Some(author) = &self.author {
+            write!(f, "\nAuthor: {}", author)?;
+        }
+        if let Some(message) = &self.message {
+            write!(f, "\nMessage: {}", message)?;
+        }
+        if let Some(sha256) = &self.archive_sha256 {
+            write!(f, "\nArchive: sha256 {}", sha256)?;
+        }
+        if let Some(signer) = &self.signer {
+            write!(f, "\nSigned by: {}", signer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Releases {
+    pub fn new<P: Into<PathBuf>>(root: P, keep: usize) -> io::Result<Self> {
+        let root = root.into();
+        create_dir_all(root.join(RELEASES_DIR))?;
+        Ok(Self {
+            root,
+            keep: keep.max(1),
+        })
+    }
+
+    pub fn current_path(&self) -> PathBuf {
+        self.root.join(CURRENT_LINK)
+    }
+
+    pub fn current(&self) -> Option<String> {
+        let target = read_link(self.current_path()).ok()?;
+        Some(target.file_name()?.to_string_lossy().into_owned())
+    }
+
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.root.join(RELEASES_DIR).join(id)
+    }
+
+    pub fn list(&self) -> io::Result<Vec<Release>> {
+        let current = self.current();
+        let mut releases = Vec::new();
+        for entry in self.root.join(RELEASES_DIR).read_dir()? {
+            let entry = entry?;
+            let id = entry.file_name().to_string_lossy().into_owned();
+            if id.starts_with('.') || !entry.file_type()?.is_dir() {
+                continue;
+            }
+            releases.push(Release {
+                active: current.as_deref() == Some(id.as_str()),
+                metadata: self.metadata(&id),
+                path: entry.path(),
+                id,
+            });
+        }
+        releases.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(releases)
+    }
+
+    pub fn create(&self) -> io::Result<PathBuf> {
+        let dir = self
+            .root
+            .join(RELEASES_DIR)
+            .join(format!(".{}", timestamp()));
+        if dir.exists() {
+            remove_dir_all(&dir)?;
+        }
+        create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    pub fn metadata(&self, id: &str) -> Option<Metadata> {
+        let content = read_to_string(self.path(id).join(METADATA_FILE)).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                warn!("Invalid metadata of release {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    pub fn add<P: AsRef<Path>>(&self, dir: P, metadata: Option<&Metadata>) -> io::Result<String> {
+        let dir = dir.as_ref();
+        let timestamp = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().trim_start_matches('.').to_string())
+            .unwrap_or_else(timestamp);
+        let commit = match metadata.and_then(|metadata| metadata.commit.as_deref()) {
+            Some(commit) => commit
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .take(SHORT_COMMIT_LEN)
+                .collect(),
+            None => String::from("unknown"),
+        };
+        let id = format!("{}-{}", timestamp, commit);
+        let path = self.path(&id);
+        if path.exists() {
+            remove_dir_all(&path)?;
+        }
+        if let Some(metadata) = metadata {
+            fs::write(
+                dir.join(METADATA_FILE),
+                serde_json::to_vec_pretty(metadata)?,
+            )?;
+        }
+        rename(dir, &path)?;
+        info!("Added release {}", id);
+        Ok(id)
+    }
+
+    pub fn activate(&self, id: &str) -> io::Result<()> {
+        if id.starts_with('.') || id.contains('/') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid release id: {}", id),
+            ));
+        }
+        let target = self.path(id);
+        if !target.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Release {} not found", id),
+            ));
+        }
+        let temp_link = self.root.join(format!(".{}.tmp", CURRENT_LINK));
+        if fs::symlink_metadata(&temp_link).is_ok() {
+            fs::remove_file(&temp_link)?;
+        }
+        symlink(Path::new(RELEASES_DIR).join(id), &temp_link)?;
+        rename(&temp_link, self.current_path())?;
+        info!("Activated release {}", id);
+        Ok(())
+    }
+
+    pub fn prune(&self) -> io::Result<Vec<String>> {
+        let mut removed = Vec::new();
+        for release in self.list()?.into_iter().skip(self.keep) {
+            if release.active {
+                continue;
+            }
+            match remove_dir_all(&release.path) {
+                Ok(_) => {
+                    info!("Removed release {}", release.id);
+                    removed.push(release.id);
+                }
+                Err(e) => warn!("Failed to remove release {}: {}", release.id, e),
+            }
+        }
+        Ok(removed)
+    }
+}
+
+fn timestamp() -> String {
+    OffsetDateTime::now_utc()
+        .format(format_description!(
+            "[year][month][day]T[hour][minute][second]"
+        ))
+        .expect("Invalid timestamp format")
+}
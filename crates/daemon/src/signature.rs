@@ -0,0 +1,154 @@
+use std::io;
+use std::sync::Arc;
+
+use ed25519_dalek::{Signature, VerifyingKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use tracing::{info, warn};
+
+use crate::config::TrustedKey;
+
+#[derive(Debug, Clone)]
+pub struct Signers {
+    keys: Arc<Vec<(String, VerifyingKey)>>,
+    required: bool,
+}
+
+impl Signers {
+    pub fn new(trusted: &[TrustedKey], required: bool) -> Result<Self, String> {
+        let mut keys = Vec::with_capacity(trusted.len());
+        for key in trusted {
+            let mut bytes = [0; PUBLIC_KEY_LENGTH];
+            hex::decode_to_slice(key.public_key.trim(), &mut bytes)
+                .map_err(|e| format!("Invalid public key of {}: {}", key.name, e))?;
+            let public_key = VerifyingKey::from_bytes(&bytes)
+                .map_err(|e| format!("Invalid public key of {}: {}", key.name, e))?;
+            keys.push((key.name.clone(), public_key));
+        }
+        if required && keys.is_empty() {
+            return Err(String::from(
+                "require_signature is set, but no trusted_keys are configured",
+            ));
+        }
+        Ok(Self {
+            keys: Arc::new(keys),
+            required,
+        })
+    }
+
+    pub fn verify(&self, sha256: &str, signature: Option<&str>) -> io::Result<Option<String>> {
+        let Some(signature) = signature else {
+            if self.required {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "Unsigned archives are refused, sign it with a trusted key",
+                ));
+            }
+            warn!("Accepting unsigned archive {}", sha256);
+            return Ok(None);
+        };
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        let digest = hex::decode(sha256)
+            .map_err(|e| invalid(format!("Invalid SHA-256 hash {}: {}", sha256, e)))?;
+        let mut bytes = [0; SIGNATURE_LENGTH];
+        hex::decode_to_slice(signature.trim(), &mut bytes)
+            .map_err(|e| invalid(format!("Invalid signature: {}", e)))?;
+        let signature = Signature::from_bytes(&bytes);
+        match self
+            .keys
+            .iter()
+            .find(|(_, key)| key.verify_strict(&digest, &signature).is_ok())
+        {
+            Some((name, _)) => {
+                info!("Archive {} signed by {}", sha256, name);
+                Ok(Some(name.clone()))
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "The archive signature does not match any trusted key",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn trusted(name: &str, key: &SigningKey) -> TrustedKey {
+        TrustedKey {
+            name: name.to_string(),
+            public_key: hex::encode(key.verifying_key().to_bytes()),
+        }
+    }
+
+    fn sign(key: &SigningKey, sha256: &str) -> String {
+        hex::encode(key.sign(&hex::decode(sha256).unwrap()).to_bytes())
+    }
+
+    fn signers(required: bool) -> Signers {
+        Signers::new(
+            &[trusted("alice", &key(1)), trusted("bob", &key(2))],
+            required,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_a_signature_from_a_trusted_key() {
+        let signature = sign(&key(2), SHA256);
+        let signer = signers(true).verify(SHA256, Some(&signature)).unwrap();
+        assert_eq!(signer.as_deref(), Some("bob"));
+    }
+
+    #[test]
+    fn rejects_a_signature_from_an_untrusted_key() {
+        let signature = sign(&key(3), SHA256);
+        let error = signers(false).verify(SHA256, Some(&signature)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn rejects_a_signature_of_another_archive() {
+        let other = hex::encode(Sha256::digest("other"));
+        let signature = sign(&key(1), &other);
+        let error = signers(false).verify(SHA256, Some(&signature)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        let error = signers(false).verify(SHA256, Some("not hex")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = signers(false).verify(SHA256, Some("abcd")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rejects_unsigned_archives_when_required() {
+        let error = signers(true).verify(SHA256, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn accepts_unsigned_archives_when_not_required() {
+        assert_eq!(signers(false).verify(SHA256, None).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        assert!(Signers::new(&[], true).is_err());
+        let invalid = TrustedKey {
+            name: String::from("mallory"),
+            public_key: String::from("abcd"),
+        };
+        assert!(Signers::new(&[invalid], false).is_err());
+    }
+}
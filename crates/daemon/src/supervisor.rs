@@ -0,0 +1,115 @@
+use std::fmt;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::config::{RestartConfig, RestartPolicy};
+use crate::BackendState;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Exit {
+    #[serde(serialize_with = "serialize_status")]
+    pub status: ExitStatus,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+    #[serde(rename = "uptime_secs", serialize_with = "serialize_secs")]
+    pub uptime: Duration,
+}
+
+fn serialize_status<S: Serializer>(status: &ExitStatus, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(status)
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} after {}s",
+            self.status,
+            self.time.format(&Rfc3339).map_err(|_| fmt::Error)?,
+            self.uptime.as_secs()
+        )
+    }
+}
+
+impl RestartConfig {
+    fn should_restart(&self, exit: &Exit) -> bool {
+        match self.policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !exit.status.success(),
+            RestartPolicy::Always => true,
+        }
+    }
+
+    fn backoff(&self, retries: u32) -> Duration {
+        let millis = self
+            .initial_backoff_millis
+            .saturating_mul(1 << retries.min(32))
+            .min(self.max_backoff_millis);
+        Duration::from_millis(millis)
+    }
+}
+
+pub async fn supervise(state: BackendState) {
+    let (name, config) = {
+        let backend = state.lock();
+        (backend.name(), backend.restart_config())
+    };
+    let mut retries = 0;
+    loop {
+        sleep(POLL_INTERVAL).await;
+        let Some(exit) = state.lock().take_exit() else {
+            continue;
+        };
+        warn!("Backend {} exited unexpectedly: {}", name, exit);
+        if exit.uptime >= STABLE_UPTIME {
+            retries = 0;
+        }
+        if !config.should_restart(&exit) {
+            info!("Restart policy is {:?}, not restarting", config.policy);
+            continue;
+        }
+        while retries < config.max_retries {
+            let backoff = config.backoff(retries);
+            retries += 1;
+            info!(
+                "Restarting backend {} in {}ms (attempt {}/{})",
+                name,
+                backoff.as_millis(),
+                retries,
+                config.max_retries
+            );
+            sleep(backoff).await;
+            if !state.lock().should_run() {
+                info!("Backend {} was stopped manually, not restarting", name);
+                break;
+            }
+            match state.start().await {
+                Ok(_) => {
+                    info!("Backend {} restarted", name);
+                    break;
+                }
+                Err(e) => warn!("Failed to restart backend {}: {}", name, e),
+            }
+        }
+        if retries >= config.max_retries && !state.running() {
+            warn!(
+                "Giving up restarting backend {} after {} retries",
+                name, retries
+            );
+            retries = 0;
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::fs::{self, create_dir_all, read_to_string, remove_dir_all, rename, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+use tracing::{info, warn};
+
+const UPLOADS_DIR: &str = "uploads";
+const STATE_FILE: &str = "upload.json";
+const ID_HASH_LEN: usize = 16;
+const EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_CHUNKS: u64 = 10_000;
+pub const MAX_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Uploads {
+    root: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Upload {
+    pub id: String,
+    pub size: u64,
+    pub sha256: String,
+    pub chunk_size: u64,
+    pub chunks: u64,
+    #[serde(default)]
+    pub received: Vec<u64>,
+}
+
+impl Upload {
+    pub fn complete(&self) -> bool {
+        self.received.len() as u64 == self.chunks
+    }
+
+    fn chunk_len(&self, index: u64) -> u64 {
+        if index + 1 == self.chunks {
+            self.size - index * self.chunk_size
+        } else {
+            self.chunk_size
+        }
+    }
+}
+
+impl Uploads {
+    pub fn new<P: Into<PathBuf>>(root: P) -> io::Result<Self> {
+        let root = root.into().join(UPLOADS_DIR);
+        create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    pub fn init(&self, size: u64, sha256: &str, chunk_size: u64) -> io::Result<Upload> {
+        if let Err(e) = self.prune() {
+            warn!("Failed to prune expired uploads: {}", e);
+        }
+        let sha256 = sha256.to_ascii_lowercase();
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid(format!("Invalid SHA-256 hash: {}", sha256)));
+        }
+        if size == 0 {
+            return Err(invalid(String::from("The upload is empty")));
+        }
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(invalid(format!(
+                "Chunk size must be between 1 and {} bytes",
+                MAX_CHUNK_SIZE
+            )));
+        }
+        if size.div_ceil(chunk_size) > MAX_CHUNKS {
+            return Err(invalid(format!(
+                "An upload has at most {} chunks, use chunks of at least {} bytes",
+                MAX_CHUNKS,
+                size.div_ceil(MAX_CHUNKS)
+            )));
+        }
+        let id = format!("{}-{}", &sha256[..ID_HASH_LEN], chunk_size);
+        if let Ok(upload) = self.get(&id) {
+            if upload.sha256 == sha256 && upload.size == size {
+                info!(
+                    "Resuming upload {} with {} of {} chunks",
+                    id,
+                    upload.received.len(),
+                    upload.chunks
+                );
+                return Ok(upload);
+            }
+        }
+        let dir = self.root.join(&id);
+        if dir.exists() {
+            remove_dir_all(&dir)?;
+        }
+        create_dir_all(&dir)?;
+        let upload = Upload {
+            id,
+            size,
+            sha256,
+            chunk_size,
+            chunks: size.div_ceil(chunk_size),
+            received: Vec::new(),
+        };
+        fs::write(dir.join(STATE_FILE), serde_json::to_vec_pretty(&upload)?)?;
+        info!("Started upload {} of {} bytes", upload.id, size);
+        Ok(upload)
+    }
+
+    pub fn get(&self, id: &str) -> io::Result<Upload> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(invalid(format!("Invalid upload id: {}", id)));
+        }
+        let dir = self.root.join(id);
+        let content = read_to_string(dir.join(STATE_FILE)).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                io::Error::new(io::ErrorKind::NotFound, format!("Upload {} not found", id))
+            }
+            _ => e,
+        })?;
+        let mut upload: Upload = serde_json::from_str(&content).map_err(io::Error::other)?;
+        upload.received = Vec::new();
+        for entry in dir.read_dir()? {
+            let entry = entry?;
+            let index = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse().ok());
+            if let Some(index) = index.filter(|index| *index < upload.chunks) {
+                if entry.file_type()?.is_file() {
+                    upload.received.push(index);
+                }
+            }
+        }
+        upload.received.sort_unstable();
+        Ok(upload)
+    }
+
+    pub fn write(&self, id: &str, index: u64, sha256: &str, bytes: &[u8]) -> io::Result<Upload> {
+        let upload = self.get(id)?;
+        if index >= upload.chunks {
+            return Err(invalid(format!(
+                "Chunk {} out of range, upload {} has {} chunks",
+                index, id, upload.chunks
+            )));
+        }
+        let expected = upload.chunk_len(index);
+        if bytes.len() as u64 != expected {
+            return Err(invalid(format!(
+                "Chunk {} has {} bytes, expected {}",
+                index,
+                bytes.len(),
+                expected
+            )));
+        }
+        let actual = hex::encode(Sha256::digest(bytes));
+        if !actual.eq_ignore_ascii_case(sha256) {
+            return Err(mismatch(format!(
+                "Chunk {} has SHA-256 {}, expected {}",
+                index, actual, sha256
+            )));
+        }
+        let dir = self.root.join(id);
+        let part = dir.join(format!("{}.part", index));
+        fs::write(&part, bytes)?;
+        rename(&part, dir.join(index.to_string()))?;
+        self.get(id)
+    }
+
+    pub fn assemble(&self, id: &str) -> io::Result<(Upload, NamedTempFile)> {
+        let upload = self.get(id)?;
+        if !upload.complete() {
+            let missing = (0..upload.chunks)
+                .filter(|index| upload.received.binary_search(index).is_err())
+                .map(|index| index.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(invalid(format!(
+                "Upload {} is missing chunks {}",
+                id, missing
+            )));
+        }
+        let dir = self.root.join(id);
+        let mut temp = NamedTempFile::new_in(&self.root)?;
+        let mut hasher = Sha256::new();
+        for index in 0..upload.chunks {
+            let mut chunk = File::open(dir.join(index.to_string()))?;
+            io::copy(&mut chunk, &mut Tee(temp.as_file_mut(), &mut hasher))?;
+        }
+        temp.as_file_mut().flush()?;
+        let actual = hex::encode(hasher.finalize());
+        if actual != upload.sha256 {
+            remove_dir_all(&dir)?;
+            return Err(mismatch(format!(
+                "Upload {} has SHA-256 {}, expected {}, upload it again",
+                id, actual, upload.sha256
+            )));
+        }
+        info!("Assembled upload {} of {} bytes", id, upload.size);
+        Ok((upload, temp))
+    }
+
+    pub fn remove(&self, id: &str) -> io::Result<()> {
+        self.get(id)?;
+        remove_dir_all(self.root.join(id))
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        let now = SystemTime::now();
+        for entry in self.root.read_dir()? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let modified = entry.metadata()?.modified()?;
+            if now.duration_since(modified).unwrap_or_default() > EXPIRY {
+                info!(
+                    "Removing expired upload {}",
+                    entry.file_name().to_string_lossy()
+                );
+                remove_dir_all(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Tee<'a>(&'a mut File, &'a mut Sha256);
+
+impl Write for Tee<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.0.write(buf)?;
+        self.1.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn mismatch(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read;
+
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    const DATA: &[u8] = b"0123456789";
+    const CHUNK_SIZE: u64 = 4;
+
+    fn sha256(bytes: &[u8]) -> String {
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    fn chunk(index: u64) -> &'static [u8] {
+        let start = (index * CHUNK_SIZE) as usize;
+        &DATA[start..DATA.len().min(start + CHUNK_SIZE as usize)]
+    }
+
+    fn start(sha256: &str) -> (TempDir, Uploads, Upload) {
+        let dir = tempdir().unwrap();
+        let uploads = Uploads::new(dir.path()).unwrap();
+        let upload = uploads.init(DATA.len() as u64, sha256, CHUNK_SIZE).unwrap();
+        (dir, uploads, upload)
+    }
+
+    fn write_all(uploads: &Uploads, upload: &Upload) {
+        for index in 0..upload.chunks {
+            let bytes = chunk(index);
+            uploads
+                .write(&upload.id, index, &sha256(bytes), bytes)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn assembles_the_chunks_in_order() {
+        let (_dir, uploads, upload) = start(&sha256(DATA));
+        assert_eq!(upload.chunks, 3);
+        for index in [2, 0, 1] {
+            let bytes = chunk(index);
+            uploads
+                .write(&upload.id, index, &sha256(bytes), bytes)
+                .unwrap();
+        }
+        let (upload, temp) = uploads.assemble(&upload.id).unwrap();
+        assert_eq!(upload.received, [0, 1, 2]);
+        assert_eq!(read(temp.path()).unwrap(), DATA);
+    }
+
+    #[test]
+    fn resumes_with_the_received_chunks() {
+        let (_dir, uploads, upload) = start(&sha256(DATA));
+        uploads
+            .write(&upload.id, 1, &sha256(chunk(1)), chunk(1))
+            .unwrap();
+        let resumed = uploads
+            .init(DATA.len() as u64, &sha256(DATA), CHUNK_SIZE)
+            .unwrap();
+        assert_eq!(resumed.id, upload.id);
+        assert_eq!(resumed.received, [1]);
+    }
+
+    #[test]
+    fn rejects_chunks_with_the_wrong_length() {
+        let (_dir, uploads, upload) = start(&sha256(DATA));
+        let bytes = &DATA[..3];
+        let error = uploads
+            .write(&upload.id, 0, &sha256(bytes), bytes)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let bytes = DATA;
+        let error = uploads
+            .write(&upload.id, 2, &sha256(bytes), bytes)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(uploads.get(&upload.id).unwrap().received.is_empty());
+    }
+
+    #[test]
+    fn rejects_chunks_with_the_wrong_hash() {
+        let (_dir, uploads, upload) = start(&sha256(DATA));
+        let error = uploads
+            .write(&upload.id, 0, &sha256(chunk(1)), chunk(0))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(uploads.get(&upload.id).unwrap().received.is_empty());
+    }
+
+    #[test]
+    fn rejects_chunks_out_of_range() {
+        let (_dir, uploads, upload) = start(&sha256(DATA));
+        let error = uploads
+            .write(&upload.id, 3, &sha256(chunk(2)), chunk(2))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rejects_incomplete_uploads() {
+        let (_dir, uploads, upload) = start(&sha256(DATA));
+        uploads
+            .write(&upload.id, 0, &sha256(chunk(0)), chunk(0))
+            .unwrap();
+        let error = uploads.assemble(&upload.id).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("missing chunks 1, 2"));
+    }
+
+    #[test]
+    fn rejects_and_removes_an_archive_with_the_wrong_hash() {
+        let (_dir, uploads, upload) = start(&sha256(b"9876543210"));
+        write_all(&uploads, &upload);
+        let error = uploads.assemble(&upload.id).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = uploads.get(&upload.id).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn rejects_invalid_uploads() {
+        let dir = tempdir().unwrap();
+        let uploads = Uploads::new(dir.path()).unwrap();
+        let hash = sha256(DATA);
+        assert!(uploads.init(10, "not a hash", CHUNK_SIZE).is_err());
+        assert!(uploads.init(0, &hash, CHUNK_SIZE).is_err());
+        assert!(uploads.init(10, &hash, 0).is_err());
+        assert!(uploads.init(10, &hash, MAX_CHUNK_SIZE + 1).is_err());
+        assert!(uploads.init(MAX_CHUNKS + 1, &hash, 1).is_err());
+        assert!(uploads.get("../escape").is_err());
+    }
+}